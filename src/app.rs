@@ -1,82 +1,509 @@
-use std::{env, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+    time::Duration,
+};
 
 use eframe::CreationContext;
-use egui::{CornerRadius, Frame, Id, Margin};
-use egui_snarl::{
-    InPinId, NodeId, OutPinId, Snarl,
-    ui::{NodeLayout, PinPlacement, SnarlStyle, SnarlWidget, get_selected_nodes},
-};
-use ouroboros::self_referencing;
+use egui::{CornerRadius, Frame, Margin, RichText};
+use egui_snarl::ui::{NodeLayout, PinPlacement, SnarlStyle};
 
 use crate::{
-    editor::{node::HyNode, viewer::HyNodeViewer},
-    generator::nodes_v1,
-    workspace::{load_descriptions, load_workspace, workspace::Workspace},
+    dialogs::{
+        diagnostics::show_diagnostics,
+        error::ErrorDialog,
+        file_browser::{FileDialog, FileDialogAction, FileDialogKind},
+        new_document::{NewDocumentAction, NewDocumentDialog},
+        settings::{SettingsAction, SettingsDialog},
+    },
+    document::{Document, REDO_SHORTCUT, UNDO_SHORTCUT},
+    settings::Settings,
+    workspace::{
+        LoadProgress,
+        registry::{WorkspaceEntry, WorkspaceRegistry},
+        watcher::WorkspaceWatcher,
+    },
 };
 
-#[self_referencing]
+/// Result of a background load
+struct Loaded {
+    registry: anyhow::Result<WorkspaceRegistry>,
+    /// File opened once the workspaces are available
+    document: Option<(PathBuf, anyhow::Result<Document>)>,
+}
+
+/// Workspaces (and possibly a file) being loaded on a background thread
+struct Loading {
+    progress: Arc<LoadProgress>,
+    receiver: mpsc::Receiver<Loaded>,
+}
+
 pub struct HyNodeEditor {
-    workspace: Workspace,
-    #[borrows(workspace)]
-    #[covariant]
-    snarl: Snarl<HyNode<'this>>,
+    /// Settings in effect, including the command line overrides
+    settings: Settings,
+    /// Settings as stored in the settings file, only changed by the user
+    stored_settings: Settings,
+    settings_dialog: Option<SettingsDialog>,
+    new_document_dialog: Option<NewDocumentDialog>,
+    file_dialog: Option<FileDialog>,
+    error_dialog: Option<ErrorDialog>,
+    /// Panel listing the files that failed to load
+    diagnostics_open: bool,
+    registry: WorkspaceRegistry,
+    documents: Vec<Document>,
+    /// Index of the document shown in the editor
+    active: usize,
+    /// Document with unsaved changes waiting for the user to confirm closing it
+    pending_close: Option<usize>,
+    /// Closing the window waits for the user to confirm discarding unsaved changes
+    pending_quit: bool,
+    /// Set once the user confirmed, so the next close request is let through
+    quit_confirmed: bool,
+    /// Shown instead of the graph if no document is open
+    status: Option<String>,
+    /// The editor shows a loading screen while this is set
+    loading: Option<Loading>,
+    /// Reload the workspaces of the open documents and the active one when their files change,
+    /// keyed by the folder of the workspace
+    watchers: HashMap<PathBuf, WorkspaceWatcher>,
 }
 
 impl HyNodeEditor {
-    pub fn build(cc: &CreationContext) -> Box<Self> {
+    /// `settings` are the `stored_settings` with the command line overrides applied
+    pub fn build(cc: &CreationContext, stored_settings: Settings, settings: Settings) -> Box<Self> {
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
-        let mut path_workspace = env::current_dir().unwrap();
-        path_workspace.push("hytale_workspaces");
-        path_workspace.push("HytaleGenerator Java");
-        // Read the directory
-        let schema = load_workspace(&path_workspace).expect("Failed to load workspace");
-        let descirption = load_descriptions(&path_workspace).expect("Failed to load descriptions");
-        let workspace = Workspace::construct(schema, descirption);
-
-        let mut path = env::current_dir().unwrap();
-        path.push("hytale_assets");
-        path.push("HytaleGenerator");
-        path.push("Biomes");
-        path.push("Basic.json");
-
-        let content = fs::read_to_string(path).expect("Could not read file");
-        let node = serde_json::from_str::<nodes_v1::RootNode>(&content).unwrap();
-        let norm = node.normalize(&workspace, "Biome").expect("Faile");
-
-        Box::new(HyNodeEditor::new(workspace, |workspace: &Workspace| {
-            let mut snarl = Snarl::new();
-
-            let (conn, nodes) = norm.0.to_editor(&workspace);
-
-            for node in nodes.into_iter() {
-                snarl.insert_node(
-                    node.pos,
-                    node.try_into()
-                        .expect("A error finializeing the node prototypes occured"),
+        let mut editor = Box::new(HyNodeEditor {
+            settings,
+            stored_settings,
+            settings_dialog: None,
+            new_document_dialog: None,
+            file_dialog: None,
+            error_dialog: None,
+            diagnostics_open: false,
+            registry: WorkspaceRegistry::default(),
+            documents: Vec::new(),
+            active: 0,
+            pending_close: None,
+            pending_quit: false,
+            quit_confirmed: false,
+            status: None,
+            loading: None,
+            watchers: HashMap::new(),
+        });
+        let file = editor.settings.file_path();
+        editor.reload(&cc.egui_ctx, file);
+        editor
+    }
+
+    /// Starts (re)loading all workspaces from the configured workspaces root on a background thread.
+    /// The file is opened afterwards, still in the background
+    pub fn reload(&mut self, ctx: &egui::Context, file: Option<PathBuf>) {
+        let progress = Arc::new(LoadProgress::default());
+        let (sender, receiver) = mpsc::channel();
+
+        let root = self.settings.workspaces_root.clone();
+        let fallback = self.settings.workspace.clone();
+        let thread_progress = progress.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let registry = WorkspaceRegistry::load(&root, &thread_progress);
+            let document = registry.as_ref().ok().zip(file).map(|(registry, path)| {
+                let document = Document::open(path.clone(), registry, &fallback);
+                (path, document)
+            });
+
+            // Fails only if the editor was closed in the meantime
+            let _ = sender.send(Loaded { registry, document });
+            ctx.request_repaint();
+        });
+
+        self.loading = Some(Loading { progress, receiver });
+    }
+
+    /// Applies the result of a background load.
+    /// Open documents are switched to the reloaded workspace matching their `$WorkspaceID`
+    fn finish_loading(&mut self, loaded: Loaded) {
+        self.registry = match loaded.registry {
+            Ok(registry) => registry,
+            Err(err) => {
+                let title = format!(
+                    "Failed to load the workspaces in {}",
+                    self.settings.workspaces_root.display()
                 );
+                self.report_error(title, err);
+                WorkspaceRegistry::default()
             }
+        };
 
-            for connection in conn.iter() {
-                snarl.connect(
-                    OutPinId {
-                        node: NodeId(connection.from_node),
-                        output: connection.from_connector,
-                    },
-                    InPinId {
-                        node: NodeId(connection.to_node),
-                        input: connection.to_connector,
-                    },
+        self.diagnostics_open = self.registry.diagnostics().next().is_some();
+
+        self.rebind_documents();
+
+        self.status = if self.registry.is_empty() {
+            Some(format!(
+                "No workspaces found in {}",
+                self.settings.workspaces_root.display()
+            ))
+        } else {
+            None
+        };
+
+        if let Some((path, document)) = loaded.document {
+            self.add_opened_document(path, document);
+        }
+    }
+
+    /// Switches open documents to the current workspace matching their `$WorkspaceID`
+    fn rebind_documents(&mut self) {
+        for document in self.documents.iter_mut() {
+            let Some(entry) = self.registry.find_for_workspace_id(document.workspace_id()) else {
+                log::warn!(
+                    "No workspace found for {}, keeping the previous one",
+                    document.path().display()
+                );
+                continue;
+            };
+
+            let missing = document.rebind(entry.workspace.clone());
+            if !missing.is_empty() {
+                log::warn!(
+                    "Nodes of {} not found in the reloaded workspace: {}",
+                    document.path().display(),
+                    missing.join(", ")
                 );
             }
+        }
+    }
+
+    /// Keeps a watcher on the folder of every workspace used by an open document and of the
+    /// active workspace, watchers of workspaces no longer in use are stopped
+    fn watch_workspaces(&mut self, ctx: &egui::Context) {
+        let mut paths: HashSet<&Path> = self
+            .documents
+            .iter()
+            .filter_map(|document| self.registry.find_for_workspace_id(document.workspace_id()))
+            .map(|entry| entry.path.as_path())
+            .collect();
+        paths.extend(
+            self.registry
+                .get_by_directory(&self.settings.workspace)
+                .map(|entry| entry.path.as_path()),
+        );
+
+        self.watchers
+            .retain(|path, _| paths.contains(path.as_path()));
+        for path in paths {
+            if !self.watchers.contains_key(path) {
+                let ctx = ctx.clone();
+                let watcher =
+                    WorkspaceWatcher::spawn(path.to_path_buf(), move || ctx.request_repaint());
+                self.watchers.insert(path.to_path_buf(), watcher);
+            }
+        }
+    }
+
+    /// Applies a workspace reloaded by the watcher of the folder `path`
+    fn apply_hot_reload(&mut self, path: &Path, entry: anyhow::Result<WorkspaceEntry>) {
+        match entry {
+            Ok(entry) => {
+                log::info!("Reloaded workspace {}", entry.directory);
+                self.diagnostics_open |= !entry.diagnostics.is_empty();
+                self.registry.replace_entry(entry);
+                self.rebind_documents();
+            }
+            Err(err) => {
+                let title = format!(
+                    "Failed to reload the workspace {}, keeping the previous version",
+                    path.display()
+                );
+                self.report_error(title, err);
+            }
+        }
+    }
+
+    fn show_loading_screen(ctx: &egui::Context, progress: &LoadProgress) {
+        let (parsed, found) = progress.files();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.heading("Loading workspaces…");
+                ui.add_space(8.0);
+                ui.add(
+                    egui::ProgressBar::new(parsed as f32 / found.max(1) as f32)
+                        .desired_width(400.0)
+                        .text(format!("{} / {} files", parsed, found))
+                        .animate(true),
+                );
+            });
+        });
+        // The counters are updated by the loading thread without notifying the UI
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+
+    fn active_document(&self) -> Option<&Document> {
+        self.documents.get(self.active)
+    }
+
+    fn active_document_mut(&mut self) -> Option<&mut Document> {
+        self.documents.get_mut(self.active)
+    }
+
+    fn add_document(&mut self, document: Document) {
+        self.documents.push(document);
+        self.active = self.documents.len() - 1;
+    }
+
+    /// Opens an asset file in a new tab (or switches to it if it is already open)
+    pub fn open_file(&mut self, path: PathBuf) {
+        if let Some(index) = self.documents.iter().position(|doc| doc.path() == &path) {
+            self.active = index;
+            return;
+        }
+
+        let document = Document::open(path.clone(), &self.registry, &self.settings.workspace);
+        self.add_opened_document(path, document);
+    }
+
+    fn add_opened_document(&mut self, path: PathBuf, document: anyhow::Result<Document>) {
+        match document {
+            Ok(document) => {
+                self.add_document(document);
+                self.remember_file(path);
+            }
+            Err(err) => {
+                self.report_error(format!("Failed to open {}", path.display()), err);
+            }
+        }
+    }
+
+    /// Saves the active document, optionally to a new path
+    pub fn save_file(&mut self, path: Option<PathBuf>) {
+        let Some(document) = self.active_document_mut() else {
+            return;
+        };
+
+        let unconnected = document.unconnected_nodes();
+        let (target, result) = match path {
+            Some(path) => (path.clone(), document.save_as(path)),
+            None => (document.path().clone(), document.save()),
+        };
+        match result {
+            Ok(()) => {
+                self.report_unconnected(&target, &unconnected);
+                self.remember_file(target);
+            }
+            Err(err) => {
+                let title = format!("Failed to save {}", target.display());
+                self.report_error(title, err);
+            }
+        }
+    }
+
+    /// Tells the user which nodes were left out of a saved file, they are still in the editor
+    fn report_unconnected(&mut self, path: &Path, unconnected: &[String]) {
+        if !unconnected.is_empty() {
+            self.error_dialog = Some(ErrorDialog::new(
+                format!("Saved {} without unconnected nodes", path.display()),
+                &anyhow::anyhow!(
+                    "{} nodes are not connected to the root and were not saved: {}",
+                    unconnected.len(),
+                    unconnected.join(", ")
+                ),
+            ));
+        }
+    }
+
+    /// Logs the error and shows it in a dialog
+    fn report_error(&mut self, title: String, err: anyhow::Error) {
+        log::error!("{}: {:#}", title, err);
+        self.error_dialog = Some(ErrorDialog::new(title, &err));
+    }
+
+    /// Closes the document, asking for confirmation first if it has unsaved changes
+    fn close_document(&mut self, index: usize, force: bool) {
+        if !force && self.documents.get(index).is_some_and(Document::is_dirty) {
+            self.pending_close = Some(index);
+            return;
+        }
+
+        if index < self.documents.len() {
+            self.documents.remove(index);
+        }
+        if self.active > index || self.active >= self.documents.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    fn show_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut close = None;
+
+        ui.horizontal_wrapped(|ui| {
+            for (index, document) in self.documents.iter().enumerate() {
+                let mut name = document.name();
+                if document.is_dirty() {
+                    name.push_str(" ●");
+                }
+
+                ui.group(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    if ui
+                        .selectable_label(index == self.active, name)
+                        .on_hover_text(document.path().display().to_string())
+                        .clicked()
+                    {
+                        self.active = index;
+                    }
+                    if ui.small_button("✕").on_hover_text("Close").clicked() {
+                        close = Some(index);
+                    }
+                });
+            }
+        });
+
+        if let Some(index) = close {
+            self.close_document(index, false);
+        }
+    }
+
+    fn show_close_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.pending_close else {
+            return;
+        };
+        let Some(document) = self.documents.get(index) else {
+            self.pending_close = None;
+            return;
+        };
+
+        let mut choice = None;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("{} has unsaved changes.", document.name()));
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending_close = None;
+                    }
+                });
+            });
 
-            snarl
-        }))
+        if let Some(save) = choice {
+            self.pending_close = None;
+            if save {
+                let document = &mut self.documents[index];
+                let unconnected = document.unconnected_nodes();
+                if let Err(err) = document.save() {
+                    let title = format!("Failed to save {}", document.path().display());
+                    self.report_error(title, err);
+                    return;
+                }
+                let path = document.path().clone();
+                self.report_unconnected(&path, &unconnected);
+            }
+            self.close_document(index, true);
+        }
+    }
+
+    /// Keeps the window open while documents have unsaved changes, until the user confirms
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|inp| inp.viewport().close_requested())
+            && !self.quit_confirmed
+            && self.documents.iter().any(Document::is_dirty)
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_quit = true;
+        }
+        if !self.pending_quit {
+            return;
+        }
+
+        let dirty = self
+            .documents
+            .iter()
+            .filter(|document| document.is_dirty())
+            .map(Document::name)
+            .collect::<Vec<_>>();
+        let mut choice = None;
+        egui::Window::new("Quit with unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!("Unsaved changes in: {}", dirty.join(", ")));
+                ui.horizontal(|ui| {
+                    if ui.button("Save all").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending_quit = false;
+                    }
+                });
+            });
+
+        let Some(save) = choice else {
+            return;
+        };
+        self.pending_quit = false;
+        if save {
+            for index in 0..self.documents.len() {
+                let document = &mut self.documents[index];
+                if !document.is_dirty() {
+                    continue;
+                }
+                if let Err(err) = document.save() {
+                    let title = format!("Failed to save {}", document.path().display());
+                    self.report_error(title, err);
+                    return;
+                }
+            }
+        }
+        self.quit_confirmed = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    /// Makes the file the one opened on the next start
+    fn remember_file(&mut self, path: PathBuf) {
+        let mut settings = self.settings.clone();
+        settings.file = Some(path);
+        self.change_settings(settings);
+    }
+
+    fn set_active_workspace(&mut self, directory: String) {
+        let mut settings = self.settings.clone();
+        settings.workspace = directory;
+        self.change_settings(settings);
+    }
+
+    /// Applies settings changed by the user. Only the changed values are written to the settings file,
+    /// so values from the command line aren't persisted
+    fn change_settings(&mut self, settings: Settings) {
+        self.stored_settings
+            .apply_changes(&self.settings, &settings);
+        self.settings = settings;
+        if let Err(err) = self.stored_settings.save() {
+            log::error!("Failed to save the settings: {}", err);
+        }
     }
 }
 
 impl eframe::App for HyNodeEditor {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.style_mut(|style| {
             style.visuals.menu_corner_radius = CornerRadius::ZERO;
             style.visuals.widgets.active.corner_radius = CornerRadius::ZERO;
@@ -90,6 +517,39 @@ impl eframe::App for HyNodeEditor {
                 .for_each(|text_style| text_style.1.size = 16.0);
         });
 
+        // Before the loading screen, which returns early
+        self.handle_close_request(ctx);
+
+        if let Some(loading) = &self.loading {
+            match loading.receiver.try_recv() {
+                Ok(loaded) => {
+                    self.loading = None;
+                    self.finish_loading(loaded);
+                }
+                Err(TryRecvError::Empty) => {
+                    Self::show_loading_screen(ctx, &loading.progress);
+                    return;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.loading = None;
+                    self.report_error(
+                        "Failed to load the workspaces".to_owned(),
+                        anyhow::anyhow!("The loading thread stopped unexpectedly"),
+                    );
+                }
+            }
+        }
+
+        self.watch_workspaces(ctx);
+        let reloads: Vec<_> = self
+            .watchers
+            .iter()
+            .filter_map(|(path, watcher)| watcher.poll().map(|entry| (path.clone(), entry)))
+            .collect();
+        for (path, entry) in reloads {
+            self.apply_hot_reload(&path, entry);
+        }
+
         let snarl_style = SnarlStyle {
             node_layout: Some(NodeLayout::coil()),
             wire_width: Some(4.0),
@@ -100,45 +560,231 @@ impl eframe::App for HyNodeEditor {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("Save").clicked() {
-                        //functionality
+                    let active = self.registry.get_by_directory(&self.settings.workspace);
+                    if ui
+                        .add_enabled(active.is_some(), egui::Button::new("New…"))
+                        .clicked()
+                        && let Some(entry) = active
+                    {
+                        self.new_document_dialog = Some(NewDocumentDialog::new(
+                            entry.clone(),
+                            &self.settings.asset_root,
+                        ));
+                    }
+                    if ui.button("Open…").clicked() {
+                        self.file_dialog = Some(FileDialog::new(
+                            FileDialogKind::Open,
+                            &self.settings.asset_root,
+                            self.active_document().map(|doc| doc.path().as_path()),
+                        ));
+                    }
+                    if ui
+                        .add_enabled(self.active_document().is_some(), egui::Button::new("Save"))
+                        .clicked()
+                    {
+                        self.save_file(None);
+                    }
+                    if ui
+                        .add_enabled(
+                            self.active_document().is_some(),
+                            egui::Button::new("Save As…"),
+                        )
+                        .clicked()
+                    {
+                        self.file_dialog = Some(FileDialog::new(
+                            FileDialogKind::SaveAs,
+                            &self.settings.asset_root,
+                            self.active_document().map(|doc| doc.path().as_path()),
+                        ));
                     }
+                    if ui
+                        .add_enabled(self.active_document().is_some(), egui::Button::new("Close"))
+                        .clicked()
+                    {
+                        self.close_document(self.active, false);
+                    }
+                    ui.separator();
+                    if ui.button("Settings…").clicked() {
+                        self.settings_dialog = Some(SettingsDialog::new(self.settings.clone()));
+                    }
+                    ui.separator();
                     if ui.button("Quit").clicked() {
-                        std::process::exit(0);
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
 
+                ui.menu_button("Edit", |ui| {
+                    let (can_undo, can_redo) = self
+                        .active_document()
+                        .map(|doc| (doc.can_undo(), doc.can_redo()))
+                        .unwrap_or_default();
+                    if ui
+                        .add_enabled(
+                            can_undo,
+                            egui::Button::new("Undo")
+                                .shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT)),
+                        )
+                        .clicked()
+                        && let Some(document) = self.active_document_mut()
+                    {
+                        document.undo();
+                    }
+                    if ui
+                        .add_enabled(
+                            can_redo,
+                            egui::Button::new("Redo")
+                                .shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT)),
+                        )
+                        .clicked()
+                        && let Some(document) = self.active_document_mut()
+                    {
+                        document.redo();
+                    }
+                });
+
+                ui.menu_button("Workspace", |ui| {
+                    let mut selected = None;
+                    for entry in self.registry.entries() {
+                        if ui
+                            .radio(self.settings.workspace == entry.directory, &entry.directory)
+                            .on_hover_text(entry.path.display().to_string())
+                            .clicked()
+                        {
+                            selected = Some(entry.directory.clone());
+                        }
+                    }
+                    if let Some(directory) = selected {
+                        self.set_active_workspace(directory);
+                    }
+                });
+
+                if let Some(document) = self.active_document_mut() {
+                    ui.separator();
+                    document.edit_title(ui);
+                }
+
+                let problems = self.registry.diagnostics().count();
+                if problems > 0
+                    && ui
+                        .selectable_label(self.diagnostics_open, format!("⚠ {}", problems))
+                        .on_hover_text("Files that failed to load")
+                        .clicked()
+                {
+                    self.diagnostics_open = !self.diagnostics_open;
+                }
+
                 ui.label(format!("Version {}", env!("CARGO_PKG_VERSION")));
             });
         });
 
+        if self.diagnostics_open {
+            egui::TopBottomPanel::bottom("diagnostics")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    self.diagnostics_open = show_diagnostics(
+                        ui,
+                        &self.settings.workspaces_root,
+                        self.registry.diagnostics(),
+                    );
+                });
+        }
+
+        if !self.documents.is_empty() {
+            egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| self.show_tabs(ui));
+        }
+
         egui::CentralPanel::default()
             .frame(Frame {
                 inner_margin: Margin::ZERO,
                 ..Default::default()
             })
-            .show(ctx, |ui| {
-                self.with_mut(|mut_self| {
-                    SnarlWidget::new()
-                        .id(Id::new("snarl-workspace"))
-                        .style(snarl_style)
-                        .show(
-                            mut_self.snarl,
-                            &mut HyNodeViewer {
-                                workspace: &mut_self.workspace,
-                            },
-                            ui,
-                        );
-                })
+            .show(ctx, |ui| match self.documents.get_mut(self.active) {
+                Some(document) => document.show(ui, snarl_style),
+                None => {
+                    ui.centered_and_justified(|ui| {
+                        ui.label(RichText::new(self.status.as_deref().unwrap_or_default()).weak());
+                    });
+                }
             });
 
-        if ctx.input(|inp| inp.key_down(egui::Key::Delete)) {
-            let nodes = get_selected_nodes(Id::new("snarl-workspace"), ctx);
-            self.with_snarl_mut(|snarl| {
-                nodes.iter().for_each(|node| {
-                    snarl.remove_node(*node);
+        if let Some(dialog) = &mut self.settings_dialog {
+            match dialog.show(ctx) {
+                Some(SettingsAction::Apply(settings)) => {
+                    self.settings_dialog = None;
+                    self.change_settings(settings);
+                    self.reload(ctx, None);
+                }
+                Some(SettingsAction::Cancel) => self.settings_dialog = None,
+                None => {}
+            }
+        }
+
+        if let Some(dialog) = &mut self.file_dialog {
+            match dialog.show(ctx) {
+                Some(FileDialogAction::Selected(kind, path)) => {
+                    self.file_dialog = None;
+                    match kind {
+                        FileDialogKind::Open => self.open_file(path),
+                        FileDialogKind::SaveAs => self.save_file(Some(path)),
+                    }
+                }
+                Some(FileDialogAction::Cancel) => self.file_dialog = None,
+                None => {}
+            }
+        }
+
+        let dropped = ctx.input(|inp| {
+            inp.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect::<Vec<_>>()
+        });
+        for path in dropped.into_iter() {
+            self.open_file(path);
+        }
+
+        if ctx.input(|inp| !inp.raw.hovered_files.is_empty()) {
+            egui::Area::new(egui::Id::new("drop-overlay"))
+                .order(egui::Order::Foreground)
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(ctx, |ui| {
+                    let rect = ctx.content_rect();
+                    ui.painter()
+                        .rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+                    ui.painter().text(
+                        rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        "Drop to open",
+                        egui::FontId::proportional(24.0),
+                        egui::Color32::WHITE,
+                    );
                 });
-            });
+        }
+
+        if let Some(dialog) = &mut self.new_document_dialog {
+            match dialog.show(ctx) {
+                Some(NewDocumentAction::Create { root, title, path }) => {
+                    let workspace = dialog.workspace().workspace.clone();
+                    self.new_document_dialog = None;
+                    match Document::create(workspace, path, &root, title) {
+                        Ok(document) => self.add_document(document),
+                        Err(err) => {
+                            self.report_error("Failed to create the document".to_owned(), err)
+                        }
+                    }
+                }
+                Some(NewDocumentAction::Cancel) => self.new_document_dialog = None,
+                None => {}
+            }
+        }
+
+        self.show_close_confirmation(ctx);
+
+        if let Some(dialog) = &self.error_dialog
+            && dialog.show(ctx)
+        {
+            self.error_dialog = None;
         }
     }
 }
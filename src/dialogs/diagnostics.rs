@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use egui::{RichText, Ui};
+
+use crate::workspace::Diagnostic;
+
+/// Lists the problems found while loading the workspaces.
+/// Returns false if the panel should be closed
+pub fn show_diagnostics<'a>(
+    ui: &mut Ui,
+    workspaces_root: &Path,
+    diagnostics: impl Iterator<Item = &'a Diagnostic>,
+) -> bool {
+    let mut open = true;
+
+    ui.horizontal(|ui| {
+        ui.strong("Problems");
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.small_button("✕").on_hover_text("Close").clicked() {
+                open = false;
+            }
+        });
+    });
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, true])
+        .max_height(200.0)
+        .show(ui, |ui| {
+            egui::Grid::new("diagnostics-grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for diagnostic in diagnostics {
+                        let path = diagnostic
+                            .path
+                            .strip_prefix(workspaces_root)
+                            .unwrap_or(&diagnostic.path);
+                        ui.label(RichText::new(path.display().to_string()).monospace())
+                            .on_hover_text(diagnostic.path.display().to_string());
+                        match diagnostic.location {
+                            Some((line, column)) => ui.label(format!("{}:{}", line, column)),
+                            None => ui.label(""),
+                        };
+                        ui.label(
+                            RichText::new(&diagnostic.message).color(ui.visuals().warn_fg_color),
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+
+    open
+}
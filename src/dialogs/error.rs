@@ -0,0 +1,50 @@
+use egui::{Context, RichText};
+
+/// Modal window showing an error and its causes, instead of only logging it
+pub struct ErrorDialog {
+    title: String,
+    causes: Vec<String>,
+}
+
+impl ErrorDialog {
+    pub fn new(title: impl Into<String>, err: &anyhow::Error) -> Self {
+        Self {
+            title: title.into(),
+            causes: err.chain().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Returns true once the dialog was closed
+    pub fn show(&self, ctx: &Context) -> bool {
+        let mut close = false;
+        let mut open = true;
+
+        egui::Window::new(RichText::new(&self.title).color(ctx.style().visuals.error_fg_color))
+            .id(egui::Id::new("error-dialog"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.set_max_width(600.0);
+                if let Some((error, causes)) = self.causes.split_first() {
+                    ui.strong(error);
+                    for cause in causes.iter() {
+                        ui.label(format!("Caused by: {}", cause));
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("OK").clicked() {
+                        close = true;
+                    }
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(self.causes.join("\n"));
+                    }
+                });
+            });
+
+        close || !open
+    }
+}
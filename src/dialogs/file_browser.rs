@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use egui::{Context, RichText, Ui};
+
+/// Minimal in-app file system browser, so no native dialog dependency is required
+pub struct FileBrowser {
+    /// Navigating above this directory is not possible
+    root: Option<PathBuf>,
+    current: PathBuf,
+    entries: Vec<(PathBuf, bool)>,
+}
+
+impl FileBrowser {
+    pub fn new(start: &Path) -> Self {
+        // Fall back to the closest existing parent if the configured path doesn't exist (yet)
+        let current = start
+            .ancestors()
+            .find(|dir| dir.is_dir())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut browser = Self {
+            root: None,
+            current,
+            entries: Vec::new(),
+        };
+        browser.refresh();
+        browser
+    }
+
+    /// Creates a browser that can't leave the given root directory
+    pub fn rooted(root: &Path, start: &Path) -> Self {
+        let start = if start.starts_with(root) { start } else { root };
+        let mut browser = Self::new(start);
+        if browser.current.starts_with(root) {
+            browser.root = Some(root.to_path_buf());
+        }
+        browser
+    }
+
+    pub fn navigate(&mut self, path: PathBuf) {
+        self.current = path;
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        self.entries = fs::read_dir(&self.current)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| (entry.path(), entry.path().is_dir()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Directories first, then alphabetically
+        self.entries
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+
+    /// Draws the current path and the listing of the current directory.
+    /// Returns the file that was clicked and whether it was a double click
+    fn show_listing(
+        &mut self,
+        ui: &mut Ui,
+        files: bool,
+        selected: &str,
+    ) -> Option<(PathBuf, bool)> {
+        let mut navigate = None;
+        let mut clicked = None;
+
+        ui.horizontal(|ui| {
+            let can_go_up = self.root.as_ref() != Some(&self.current);
+            if ui
+                .add_enabled(can_go_up, egui::Button::new("⬆"))
+                .on_hover_text("Parent directory")
+                .clicked()
+            {
+                navigate = self.current.parent().map(Path::to_path_buf);
+            }
+            ui.label(RichText::new(self.current.display().to_string()).monospace());
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (path, is_dir) in self.entries.iter() {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy())
+                        .unwrap_or_default();
+
+                    if *is_dir {
+                        if ui
+                            .selectable_label(false, format!("🗀 {}", name))
+                            .double_clicked()
+                        {
+                            navigate = Some(path.clone());
+                        }
+                    } else if files && is_asset_file(path) {
+                        let response = ui.selectable_label(name == selected, format!("🗋 {}", name));
+                        if response.double_clicked() {
+                            clicked = Some((path.clone(), true));
+                        } else if response.clicked() {
+                            clicked = Some((path.clone(), false));
+                        }
+                    }
+                }
+            });
+
+        ui.separator();
+
+        if let Some(path) = navigate {
+            self.navigate(path);
+        }
+
+        clicked
+    }
+
+    /// Draws the directory listing. Returns the path of a directory the user selected
+    pub fn show_directories(&mut self, ui: &mut Ui) -> Option<PathBuf> {
+        self.show_listing(ui, false, "");
+
+        if ui.button("Select this directory").clicked() {
+            Some(self.current.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Draws the listing including asset files and a file name field.
+    /// Returns the chosen file once it was double clicked or confirmed
+    pub fn show_files(
+        &mut self,
+        ui: &mut Ui,
+        file_name: &mut String,
+        confirm: &str,
+    ) -> Option<PathBuf> {
+        let mut chosen = None;
+
+        if let Some((path, double_click)) = self.show_listing(ui, true, file_name) {
+            *file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if double_click {
+                chosen = Some(path);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(file_name).desired_width(300.0));
+            if ui
+                .add_enabled(!file_name.trim().is_empty(), egui::Button::new(confirm))
+                .clicked()
+            {
+                chosen = Some(self.current.join(file_name.trim()));
+            }
+        });
+
+        chosen
+    }
+}
+
+fn is_asset_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileDialogKind {
+    Open,
+    SaveAs,
+}
+
+pub enum FileDialogAction {
+    Selected(FileDialogKind, PathBuf),
+    Cancel,
+}
+
+/// Open / Save As window built on top of the [`FileBrowser`]
+pub struct FileDialog {
+    kind: FileDialogKind,
+    browser: FileBrowser,
+    file_name: String,
+    /// Existing file selected in the Save As dialog, waiting for the user to confirm replacing it
+    overwrite: Option<PathBuf>,
+}
+
+impl FileDialog {
+    pub fn new(kind: FileDialogKind, asset_root: &Path, current: Option<&Path>) -> Self {
+        let start = current.and_then(Path::parent).unwrap_or(asset_root);
+        let file_name = current
+            .filter(|_| kind == FileDialogKind::SaveAs)
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self {
+            kind,
+            browser: FileBrowser::rooted(asset_root, start),
+            file_name,
+            overwrite: None,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> Option<FileDialogAction> {
+        let mut action = None;
+        let mut open = true;
+
+        let (title, confirm) = match self.kind {
+            FileDialogKind::Open => ("Open", "Open"),
+            FileDialogKind::SaveAs => ("Save As", "Save"),
+        };
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(path) = self.overwrite.clone() {
+                    ui.label(format!("{} already exists. Replace it?", path.display()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Replace").clicked() {
+                            action = Some(FileDialogAction::Selected(self.kind, path));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.overwrite = None;
+                        }
+                    });
+                } else if let Some(mut path) =
+                    self.browser.show_files(ui, &mut self.file_name, confirm)
+                {
+                    if self.kind == FileDialogKind::SaveAs && path.extension().is_none() {
+                        path.set_extension("json");
+                    }
+                    if self.kind == FileDialogKind::SaveAs && path.exists() {
+                        self.overwrite = Some(path);
+                    } else {
+                        action = Some(FileDialogAction::Selected(self.kind, path));
+                    }
+                }
+            });
+
+        if !open {
+            action = Some(FileDialogAction::Cancel);
+        }
+
+        action
+    }
+}
@@ -0,0 +1,5 @@
+pub mod diagnostics;
+pub mod error;
+pub mod file_browser;
+pub mod new_document;
+pub mod settings;
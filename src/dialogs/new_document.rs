@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+
+use egui::Context;
+
+use crate::workspace::registry::WorkspaceEntry;
+
+pub enum NewDocumentAction {
+    /// Create a document for the root (key in the `Roots` table)
+    Create {
+        root: String,
+        title: String,
+        path: PathBuf,
+    },
+    Cancel,
+}
+
+/// Window for starting a new graph from one of the roots of a workspace
+pub struct NewDocumentDialog {
+    entry: WorkspaceEntry,
+    roots: Vec<(String, String)>,
+    root: Option<String>,
+    title: String,
+    file_name: String,
+    asset_root: PathBuf,
+    /// Existing file the document would be saved to, waiting for the user to confirm replacing it
+    overwrite: Option<PathBuf>,
+}
+
+impl NewDocumentDialog {
+    pub fn new(entry: WorkspaceEntry, asset_root: &Path) -> Self {
+        let mut roots = entry
+            .workspace
+            .workspace
+            .roots
+            .iter()
+            .map(|(key, root)| (key.clone(), root.menu_name.clone()))
+            .collect::<Vec<_>>();
+        roots.sort_by(|a, b| a.1.cmp(&b.1));
+
+        Self {
+            entry,
+            root: roots.first().map(|root| root.0.clone()),
+            roots,
+            title: String::new(),
+            file_name: String::new(),
+            asset_root: asset_root.to_path_buf(),
+            overwrite: None,
+        }
+    }
+
+    pub fn workspace(&self) -> &WorkspaceEntry {
+        &self.entry
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> Option<NewDocumentAction> {
+        let mut action = None;
+        let mut open = true;
+
+        egui::Window::new(format!("New document ({})", self.entry.directory))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(path) = self.overwrite.clone() {
+                    ui.label(format!("{} already exists. Replace it?", path.display()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Replace").clicked() {
+                            action = self.create(path);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.overwrite = None;
+                        }
+                    });
+                    return;
+                }
+
+                ui.strong("Type");
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (key, menu_name) in self.roots.iter() {
+                            ui.radio_value(&mut self.root, Some(key.clone()), menu_name);
+                        }
+                    });
+
+                ui.separator();
+                egui::Grid::new("new-document-grid")
+                    .num_columns(2)
+                    .spacing([8.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label("Title");
+                        ui.text_edit_singleline(&mut self.title);
+                        ui.end_row();
+
+                        ui.label("File");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.file_name)
+                                .hint_text(format!("{}.json", self.title)),
+                        )
+                        .on_hover_text(format!(
+                            "Relative to the asset directory {}",
+                            self.asset_root.display()
+                        ));
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let valid = self.root.is_some() && !self.title.trim().is_empty();
+                    if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+                        let file_name = if self.file_name.trim().is_empty() {
+                            format!("{}.json", self.title.trim())
+                        } else {
+                            self.file_name.trim().to_owned()
+                        };
+
+                        // The first save would replace the file without asking
+                        let path = self.asset_root.join(file_name);
+                        if path.exists() {
+                            self.overwrite = Some(path);
+                        } else {
+                            action = self.create(path);
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        action = Some(NewDocumentAction::Cancel);
+                    }
+                });
+            });
+
+        if !open {
+            action = Some(NewDocumentAction::Cancel);
+        }
+
+        action
+    }
+
+    fn create(&self, path: PathBuf) -> Option<NewDocumentAction> {
+        Some(NewDocumentAction::Create {
+            root: self.root.clone()?,
+            title: self.title.trim().to_owned(),
+            path,
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use egui::{Context, Ui};
+
+use crate::{dialogs::file_browser::FileBrowser, settings::Settings};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathField {
+    WorkspacesRoot,
+    AssetRoot,
+}
+
+pub enum SettingsAction {
+    Apply(Settings),
+    Cancel,
+}
+
+/// Window for picking the workspace and asset directories
+pub struct SettingsDialog {
+    settings: Settings,
+    browser: Option<(PathField, FileBrowser)>,
+}
+
+impl SettingsDialog {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            browser: None,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &Context) -> Option<SettingsAction> {
+        let mut action = None;
+        let mut open = true;
+
+        egui::Window::new("Settings")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings-grid")
+                    .num_columns(3)
+                    .spacing([8.0, 8.0])
+                    .show(ui, |ui| {
+                        ui.label("Workspaces");
+                        path_edit(ui, &mut self.settings.workspaces_root);
+                        if ui.button("Browse…").clicked() {
+                            self.browser = Some((
+                                PathField::WorkspacesRoot,
+                                FileBrowser::new(&self.settings.workspaces_root),
+                            ));
+                        }
+                        ui.end_row();
+
+                        ui.label("Active workspace");
+                        egui::ComboBox::from_id_salt("settings-workspace")
+                            .selected_text(&self.settings.workspace)
+                            .width(400.0)
+                            .show_ui(ui, |ui| {
+                                for name in self.settings.available_workspaces() {
+                                    ui.selectable_value(
+                                        &mut self.settings.workspace,
+                                        name.clone(),
+                                        name,
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Assets");
+                        path_edit(ui, &mut self.settings.asset_root);
+                        if ui.button("Browse…").clicked() {
+                            self.browser = Some((
+                                PathField::AssetRoot,
+                                FileBrowser::new(&self.settings.asset_root),
+                            ));
+                        }
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        action = Some(SettingsAction::Apply(self.settings.clone()));
+                    }
+                    if ui.button("Cancel").clicked() {
+                        action = Some(SettingsAction::Cancel);
+                    }
+                });
+            });
+
+        if let Some((field, browser)) = &mut self.browser {
+            let mut browser_open = true;
+            let mut selected = None;
+            egui::Window::new("Select directory")
+                .collapsible(false)
+                .open(&mut browser_open)
+                .show(ctx, |ui| {
+                    selected = browser.show_directories(ui);
+                });
+
+            if let Some(path) = selected {
+                match field {
+                    PathField::WorkspacesRoot => self.settings.workspaces_root = path,
+                    PathField::AssetRoot => self.settings.asset_root = path,
+                }
+                self.browser = None;
+            } else if !browser_open {
+                self.browser = None;
+            }
+        }
+
+        if !open {
+            action = Some(SettingsAction::Cancel);
+        }
+
+        action
+    }
+}
+
+fn path_edit(ui: &mut Ui, path: &mut PathBuf) {
+    let mut text = path.display().to_string();
+    if ui
+        .add(egui::TextEdit::singleline(&mut text).desired_width(400.0))
+        .changed()
+    {
+        *path = PathBuf::from(text);
+    }
+}
@@ -0,0 +1,624 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use egui::{Id, Key, KeyboardShortcut, Modifiers, Pos2, Ui, Vec2, pos2};
+use egui_snarl::{
+    InPinId, NodeId, OutPinId, Snarl,
+    ui::{SnarlStyle, SnarlWidget, get_selected_nodes},
+};
+use indexmap::IndexMap;
+
+use crate::{
+    editor::{
+        history::{EditCommand, History, find_node},
+        node::HyNode,
+        viewer::HyNodeViewer,
+    },
+    generator::{
+        GeneratorError,
+        common::{self, WorksheetInfo},
+        loader::RawAsset,
+        nodes_v2,
+        norm::NormalizedNode,
+    },
+    workspace::{
+        nodes::{Connector, NodeDescription},
+        registry::WorkspaceRegistry,
+        workspace::Workspace,
+    },
+};
+
+pub const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+pub const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+const REDO_SHORTCUT_ALT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+
+static NEXT_DOCUMENT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A single asset file opened in the editor together with the workspace its nodes are described by
+pub struct Document {
+    workspace: Arc<Workspace>,
+    path: PathBuf,
+    info: WorksheetInfo,
+    /// Moved away from the file positions on load, added back when saving
+    offset: Vec2,
+    /// Asset node id of the root, graph ids aren't stable across undo / redo
+    root: common::NodeId,
+    /// Unique id of the graph widget, so every document keeps its own viewport
+    id: Id,
+    /// Modified since it was last loaded or saved
+    dirty: bool,
+    snarl: Snarl<HyNode>,
+    history: History,
+}
+
+impl Document {
+    /// Opens an asset file using the workspace matching its `$WorkspaceID`.
+    /// The fallback workspace (folder name) is used if the id doesn't match any workspace
+    pub fn open(
+        path: PathBuf,
+        registry: &WorkspaceRegistry,
+        fallback: &str,
+    ) -> anyhow::Result<Self> {
+        let asset = RawAsset::read(&path)?;
+        let entry = asset
+            .workspace_id()
+            .and_then(|id| registry.find_for_workspace_id(id))
+            .or_else(|| registry.get_by_directory(fallback))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No workspace found for the workspace id {:?}",
+                    asset.workspace_id().unwrap_or_default()
+                )
+            })?;
+
+        Self::from_asset(entry.workspace.clone(), path, asset)
+    }
+
+    pub fn from_asset(
+        workspace: Arc<Workspace>,
+        path: PathBuf,
+        asset: RawAsset,
+    ) -> anyhow::Result<Self> {
+        let version = asset.version;
+        let (norm, info) = asset.normalize(&workspace)?;
+        log::info!("Loaded {} ({:?})", path.display(), version);
+
+        let mut snarl = Snarl::<HyNode>::new();
+        let (conn, nodes, offset) = norm.to_editor(&workspace)?;
+
+        for node in nodes.into_iter() {
+            snarl.insert_node(node.pos, node.try_into()?);
+        }
+
+        for connection in conn.iter() {
+            snarl.connect(
+                OutPinId {
+                    node: NodeId(connection.from_node),
+                    output: connection.from_connector,
+                },
+                InPinId {
+                    node: NodeId(connection.to_node),
+                    input: connection.to_connector,
+                },
+            );
+        }
+
+        // The root is always the first node created by to_editor
+        let root = snarl
+            .get_node(NodeId(0))
+            .map(|node| node.node_id.clone())
+            .ok_or(GeneratorError::EditorNodeMissing(0))?;
+
+        Ok(Self {
+            workspace,
+            path,
+            info,
+            offset,
+            root,
+            id: Self::next_id(),
+            dirty: false,
+            snarl,
+            history: History::default(),
+        })
+    }
+
+    /// Creates a new document containing only the root node of the given root (key in the `Roots` table)
+    pub fn create(
+        workspace: Arc<Workspace>,
+        path: PathBuf,
+        root_key: &str,
+        title: String,
+    ) -> anyhow::Result<Self> {
+        let root_type = workspace
+            .workspace
+            .roots
+            .get(root_key)
+            .map(|root| root.root_node_type.clone())
+            .ok_or_else(|| GeneratorError::RootNotResolved(root_key.to_owned(), Vec::new()))?;
+        let description = workspace
+            .get_description(&root_type)
+            .cloned()
+            .ok_or_else(|| GeneratorError::NodeVariantResolve(root_type.clone()))?;
+
+        let root = HyNode::new(description);
+        let mut snarl = Snarl::new();
+        let root_id = root.node_id.clone();
+        snarl.insert_node(pos2(0.0, 0.0), root);
+
+        Ok(Self {
+            info: WorksheetInfo {
+                title,
+                workspace_id: format!("{} - {}", workspace.workspace.workspace_name, root_key),
+                groups: Vec::new(),
+                metadata: IndexMap::new(),
+            },
+            offset: Vec2::ZERO,
+            workspace,
+            path,
+            root: root_id,
+            id: Self::next_id(),
+            dirty: true,
+            snarl,
+            history: History::default(),
+        })
+    }
+
+    fn next_id() -> Id {
+        Id::new((
+            "snarl-workspace",
+            NEXT_DOCUMENT_ID.fetch_add(1, Ordering::Relaxed),
+        ))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Name shown in the tab
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.info.title.clone())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// The `$WorkspaceID` of the asset
+    pub fn workspace_id(&self) -> &str {
+        &self.info.workspace_id
+    }
+
+    /// Switches the document to a (reloaded) workspace, keeping the graph.
+    /// Returns the ids of the descriptors that no longer exist.
+    /// Those nodes keep their old description and are flagged as missing
+    pub fn rebind(&mut self, workspace: Arc<Workspace>) -> Vec<String> {
+        if Arc::ptr_eq(&self.workspace, &workspace) {
+            return Vec::new();
+        }
+
+        let previous = self
+            .snarl
+            .node_ids()
+            .map(|(id, node)| (id, node.description.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut missing = Vec::new();
+        for node in self.snarl.nodes_mut() {
+            if !node.rebind(&workspace) {
+                missing.push(node.description.id.clone());
+            }
+        }
+        self.remap_wires(&previous);
+        // The recorded nodes and wires still refer to the previous descriptions
+        self.history.clear();
+
+        self.workspace = workspace;
+        missing
+    }
+
+    /// Moves the wires to the pins with the same connector id after the descriptions of the nodes
+    /// changed, wires of pins that no longer exist are removed
+    fn remap_wires(&mut self, previous: &HashMap<NodeId, Arc<NodeDescription>>) {
+        let mut moved = Vec::new();
+        for (out_pin, in_pin) in self.snarl.wires().collect::<Vec<_>>() {
+            let output = remap_pin(
+                &previous[&out_pin.node].outputs,
+                &self.snarl[out_pin.node].description.outputs,
+                out_pin.output,
+            );
+            let input = remap_pin(
+                &previous[&in_pin.node].inputs,
+                &self.snarl[in_pin.node].description.inputs,
+                in_pin.input,
+            );
+            if output == Some(out_pin.output) && input == Some(in_pin.input) {
+                continue;
+            }
+
+            self.snarl.disconnect(out_pin, in_pin);
+            match output.zip(input) {
+                Some((output, input)) => moved.push((
+                    OutPinId {
+                        node: out_pin.node,
+                        output,
+                    },
+                    InPinId {
+                        node: in_pin.node,
+                        input,
+                    },
+                )),
+                None => log::warn!(
+                    "Removed the wire from {} to {} of {}, the pin no longer exists",
+                    self.snarl[out_pin.node].title,
+                    self.snarl[in_pin.node].title,
+                    self.path.display()
+                ),
+            }
+        }
+
+        // Connected after all old wires are gone, so a moved wire can't be removed again
+        for (out_pin, in_pin) in moved {
+            self.snarl.connect(out_pin, in_pin);
+        }
+    }
+
+    /// Titles of the nodes that aren't connected to the root, the asset file can't contain them
+    pub fn unconnected_nodes(&self) -> Vec<String> {
+        let mut connected = HashSet::new();
+        let mut pending = find_node(&self.snarl, &self.root)
+            .into_iter()
+            .collect::<Vec<_>>();
+        while let Some(id) = pending.pop() {
+            if connected.insert(id) {
+                pending.extend(
+                    self.snarl
+                        .wires()
+                        .filter(|(out_pin, _)| out_pin.node == id)
+                        .map(|(_, in_pin)| in_pin.node),
+                );
+            }
+        }
+
+        self.snarl
+            .node_ids()
+            .filter(|(id, _)| !connected.contains(id))
+            .map(|(_, node)| node.title.clone())
+            .collect()
+    }
+
+    /// Writes the current graph back to the asset file (always using the v2 format).
+    /// Nodes not connected to the root are left out, see [`Document::unconnected_nodes`]
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let path = self.path.clone();
+        self.write(&path)
+    }
+
+    /// Saves to a new file, the document only switches to it once it was written
+    pub fn save_as(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        self.write(&path)?;
+        self.path = path;
+        Ok(())
+    }
+
+    fn write(&mut self, path: &Path) -> anyhow::Result<()> {
+        let root = find_node(&self.snarl, &self.root)
+            .ok_or_else(|| GeneratorError::NodeVariantResolve(self.root.0.clone()))?;
+        let norm = NormalizedNode::from_editor(&self.snarl, root, &self.workspace, self.offset)?;
+        let root = nodes_v2::RootNode::from_normalized(norm, &self.info, &self.workspace)?;
+
+        let unconnected = self.unconnected_nodes();
+        if !unconnected.is_empty() {
+            log::warn!(
+                "Leaving out the nodes of {} not connected to the root: {}",
+                path.display(),
+                unconnected.join(", ")
+            );
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&root)?)?;
+        log::info!("Saved {}", path.display());
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Edits the `$Title` of the document
+    pub fn edit_title(&mut self, ui: &mut Ui) {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.info.title)
+                .hint_text("Title")
+                .desired_width(200.0),
+        );
+        self.dirty |= response.changed();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    pub fn undo(&mut self) {
+        self.dirty |= self.history.undo(&mut self.snarl);
+    }
+
+    pub fn redo(&mut self) {
+        self.dirty |= self.history.redo(&mut self.snarl);
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, style: SnarlStyle) {
+        let revision = self.history.revision();
+        let time = ui.input(|inp| inp.time);
+        let positions = node_positions(&self.snarl);
+        let mut viewer = HyNodeViewer {
+            workspace: &self.workspace,
+            root: &self.root,
+            history: &mut self.history,
+            time,
+        };
+
+        SnarlWidget::new()
+            .id(self.id)
+            .style(style)
+            .show(&mut self.snarl, &mut viewer, ui);
+
+        // Moving nodes doesn't go through the viewer, so compare the positions instead
+        let moves = positions
+            .into_iter()
+            .filter_map(|(id, before)| {
+                let info = self.snarl.get_node_info(id)?;
+                (info.pos != before).then(|| (info.value.node_id.clone(), before, info.pos))
+            })
+            .collect::<Vec<_>>();
+        if !moves.is_empty() {
+            self.history.record(EditCommand::MoveNodes(moves), time);
+        }
+
+        // Text fields handle these keys themselves
+        if !ui.ctx().wants_keyboard_input() {
+            if ui.input(|inp| inp.key_pressed(egui::Key::Delete)) {
+                let nodes = get_selected_nodes(self.id, ui.ctx());
+                if let Some(command) = EditCommand::remove_nodes(&self.snarl, &nodes, &self.root) {
+                    self.history.execute(&mut self.snarl, command, time);
+                }
+            }
+
+            if ui.input_mut(|inp| {
+                inp.consume_shortcut(&REDO_SHORTCUT) || inp.consume_shortcut(&REDO_SHORTCUT_ALT)
+            }) {
+                self.history.redo(&mut self.snarl);
+            } else if ui.input_mut(|inp| inp.consume_shortcut(&UNDO_SHORTCUT)) {
+                self.history.undo(&mut self.snarl);
+            }
+        }
+
+        self.dirty |= self.history.revision() != revision;
+    }
+}
+
+fn node_positions(snarl: &Snarl<HyNode>) -> Vec<(NodeId, Pos2)> {
+    snarl
+        .nodes_pos_ids()
+        .map(|(id, pos, _)| (id, pos))
+        .collect()
+}
+
+/// Index of the connector with the same id as `previous[index]` in `current`
+fn remap_pin(previous: &[Connector], current: &[Connector], index: usize) -> Option<usize> {
+    let id = &previous.get(index)?.id;
+    current.iter().position(|connector| &connector.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, sync::Arc};
+
+    use egui::pos2;
+    use egui_snarl::{InPinId, OutPinId};
+
+    use crate::{
+        document::Document,
+        editor::{
+            history::{EditCommand, Wire},
+            node::HyNode,
+        },
+        workspace::{tests::synthetic_workspace, workspace::Workspace},
+    };
+
+    #[test]
+    fn unconnected_nodes() {
+        let workspace = Arc::new(synthetic_workspace());
+        let mut document = Document::create(
+            workspace.clone(),
+            PathBuf::from("Test.json"),
+            "Biome",
+            "Test".into(),
+        )
+        .unwrap();
+        let root = document.snarl.node_ids().next().unwrap().0;
+
+        let constant = workspace
+            .get_description("ConstantDensity")
+            .unwrap()
+            .clone();
+        let connected = document
+            .snarl
+            .insert_node(pos2(100.0, 0.0), HyNode::new(constant.clone()));
+        document
+            .snarl
+            .insert_node(pos2(100.0, 100.0), HyNode::new(constant));
+        document.snarl.connect(
+            OutPinId {
+                node: root,
+                output: 0,
+            },
+            InPinId {
+                node: connected,
+                input: 0,
+            },
+        );
+
+        assert_eq!(document.unconnected_nodes(), vec!["Constant".to_owned()]);
+    }
+
+    #[test]
+    fn rebind_remaps_wires() {
+        let workspace = Arc::new(synthetic_workspace());
+        let mut document = Document::create(
+            workspace.clone(),
+            PathBuf::from("Test.json"),
+            "Biome",
+            "Test".into(),
+        )
+        .unwrap();
+        let root = document.snarl.node_ids().next().unwrap().0;
+
+        let sum = document.snarl.insert_node(
+            pos2(100.0, 0.0),
+            HyNode::new(workspace.get_description("SumDensity").unwrap().clone()),
+        );
+        let constant = document.snarl.insert_node(
+            pos2(200.0, 0.0),
+            HyNode::new(
+                workspace
+                    .get_description("ConstantDensity")
+                    .unwrap()
+                    .clone(),
+            ),
+        );
+        let terrain = OutPinId {
+            node: root,
+            output: 0,
+        };
+        let sum_input = InPinId {
+            node: sum,
+            input: 0,
+        };
+        let constant_input = InPinId {
+            node: constant,
+            input: 0,
+        };
+        document.snarl.connect(terrain, sum_input);
+        let wire = Wire::new(
+            &document.snarl,
+            OutPinId {
+                node: sum,
+                output: 0,
+            },
+            constant_input,
+        )
+        .unwrap();
+        document
+            .history
+            .execute(&mut document.snarl, EditCommand::Connect(wire), 0.0);
+
+        // Sum loses its input and gets a new output in front of Inputs
+        let nodes = workspace
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut node = (**node).clone();
+                if node.id == "SumDensity" {
+                    node.inputs.clear();
+                    node.outputs.insert(0, node.outputs[0].clone());
+                    node.outputs[0].id = "Extra".to_owned();
+                }
+                node
+            })
+            .collect();
+        let reloaded = Workspace::construct(workspace.workspace.clone(), nodes);
+        assert!(document.rebind(Arc::new(reloaded)).is_empty());
+        assert!(!document.can_undo());
+
+        let wires = document.snarl.wires().collect::<Vec<_>>();
+        assert_eq!(
+            wires,
+            vec![(
+                OutPinId {
+                    node: sum,
+                    output: 1,
+                },
+                constant_input,
+            )]
+        );
+    }
+
+    #[test]
+    fn save_with_missing_descriptor() {
+        let workspace = Arc::new(synthetic_workspace());
+        let mut document = Document::create(
+            workspace.clone(),
+            PathBuf::from("Test.json"),
+            "Biome",
+            "Test".into(),
+        )
+        .unwrap();
+        let root = document.snarl.node_ids().next().unwrap().0;
+        let sum = document.snarl.insert_node(
+            pos2(100.0, 0.0),
+            HyNode::new(workspace.get_description("SumDensity").unwrap().clone()),
+        );
+        document.snarl.connect(
+            OutPinId {
+                node: root,
+                output: 0,
+            },
+            InPinId {
+                node: sum,
+                input: 0,
+            },
+        );
+
+        // The node keeps its previous description and is still written
+        let nodes = workspace
+            .nodes
+            .iter()
+            .filter(|node| node.id != "SumDensity")
+            .map(|node| (**node).clone())
+            .collect();
+        let reloaded = Workspace::construct(workspace.workspace.clone(), nodes);
+        assert_eq!(document.rebind(Arc::new(reloaded)), vec!["SumDensity"]);
+
+        let target = env::temp_dir().join(format!("hynode-missing-{}.json", std::process::id()));
+        document.save_as(target.clone()).unwrap();
+        let saved = fs::read_to_string(&target).unwrap();
+        fs::remove_file(&target).unwrap();
+        assert!(saved.contains("SumDensity-"));
+    }
+
+    #[test]
+    fn failed_save_as_keeps_path() {
+        let workspace = Arc::new(synthetic_workspace());
+        let mut document = Document::create(
+            workspace,
+            PathBuf::from("Test.json"),
+            "Biome",
+            "Test".into(),
+        )
+        .unwrap();
+
+        // A file can't be created below another file
+        let target = env::temp_dir().join(format!("hynode-save-as-{}", std::process::id()));
+        fs::write(&target, "").unwrap();
+        assert!(document.save_as(target.join("Test.json")).is_err());
+        fs::remove_file(&target).unwrap();
+
+        assert_eq!(document.path(), &PathBuf::from("Test.json"));
+        assert!(document.is_dirty());
+    }
+}
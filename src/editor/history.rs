@@ -0,0 +1,530 @@
+use egui::Pos2;
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+
+use crate::{
+    editor::{
+        node::{HyNode, ordered_children},
+        value::NodeEditorValueTypes,
+    },
+    generator::common,
+};
+
+/// Edits of the same value (or drags of the same nodes) within this many seconds are merged into one step
+const MERGE_WINDOW: f64 = 1.0;
+
+/// A wire between two pins. Nodes are referenced by their asset node id,
+/// as the graph ids change when a removed node is inserted again
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wire {
+    pub from: common::NodeId,
+    pub output: usize,
+    pub to: common::NodeId,
+    pub input: usize,
+}
+
+impl Wire {
+    pub fn new(snarl: &Snarl<HyNode>, from: OutPinId, to: InPinId) -> Option<Self> {
+        Some(Self {
+            from: snarl.get_node(from.node)?.node_id.clone(),
+            output: from.output,
+            to: snarl.get_node(to.node)?.node_id.clone(),
+            input: to.input,
+        })
+    }
+
+    fn resolve(&self, snarl: &Snarl<HyNode>) -> Option<(OutPinId, InPinId)> {
+        Some((
+            OutPinId {
+                node: find_node(snarl, &self.from)?,
+                output: self.output,
+            },
+            InPinId {
+                node: find_node(snarl, &self.to)?,
+                input: self.input,
+            },
+        ))
+    }
+}
+
+/// A single reversible change to the graph
+#[derive(Debug, Clone)]
+pub enum EditCommand {
+    AddNode {
+        pos: Pos2,
+        node: HyNode,
+    },
+    RemoveNodes {
+        nodes: Vec<(Pos2, HyNode)>,
+        wires: Vec<Wire>,
+    },
+    Connect(Wire),
+    Disconnect(Vec<Wire>),
+    /// Node id, position before and after
+    MoveNodes(Vec<(common::NodeId, Pos2, Pos2)>),
+    SetValue {
+        node: common::NodeId,
+        /// Content id of the node description, indices change when the workspace is reloaded
+        content: String,
+        before: NodeEditorValueTypes,
+        after: NodeEditorValueTypes,
+    },
+    /// Order of the children connected to an output (by connector id) before and after
+    ReorderChildren {
+        node: common::NodeId,
+        output: String,
+        before: Vec<common::NodeId>,
+        after: Vec<common::NodeId>,
+    },
+}
+
+impl EditCommand {
+    /// Captures the nodes and all wires connected to them, so they can be restored.
+    /// The root node of the document is never removed, a file can't be saved without it
+    pub fn remove_nodes(
+        snarl: &Snarl<HyNode>,
+        ids: &[NodeId],
+        root: &common::NodeId,
+    ) -> Option<Self> {
+        let ids = ids
+            .iter()
+            .copied()
+            .filter(|id| {
+                snarl
+                    .get_node(*id)
+                    .is_some_and(|node| node.node_id != *root)
+            })
+            .collect::<Vec<_>>();
+        let nodes = ids
+            .iter()
+            .filter_map(|id| snarl.get_node_info(*id))
+            .map(|info| (info.pos, info.value.clone()))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            return None;
+        }
+
+        let wires = snarl
+            .wires()
+            .filter(|(from, to)| ids.contains(&from.node) || ids.contains(&to.node))
+            .filter_map(|(from, to)| Wire::new(snarl, from, to))
+            .collect();
+
+        Some(Self::RemoveNodes { nodes, wires })
+    }
+
+    /// Captures all wires connected to the input pin
+    pub fn drop_inputs(snarl: &Snarl<HyNode>, pin: InPinId) -> Option<Self> {
+        let wires = snarl
+            .in_pin(pin)
+            .remotes
+            .iter()
+            .filter_map(|remote| Wire::new(snarl, *remote, pin))
+            .collect::<Vec<_>>();
+        (!wires.is_empty()).then_some(Self::Disconnect(wires))
+    }
+
+    /// Captures all wires connected to the output pin
+    pub fn drop_outputs(snarl: &Snarl<HyNode>, pin: OutPinId) -> Option<Self> {
+        let wires = snarl
+            .out_pin(pin)
+            .remotes
+            .iter()
+            .filter_map(|remote| Wire::new(snarl, pin, *remote))
+            .collect::<Vec<_>>();
+        (!wires.is_empty()).then_some(Self::Disconnect(wires))
+    }
+
+    /// Moves the child at index `from` of the output to index `to`, fixing the order of all its children
+    pub fn move_child(
+        snarl: &Snarl<HyNode>,
+        pin: OutPinId,
+        from: usize,
+        to: usize,
+    ) -> Option<Self> {
+        let node = snarl.get_node(pin.node)?;
+        let output = node.description.outputs.get(pin.output)?.id.clone();
+        let before = ordered_children(snarl, pin)
+            .into_iter()
+            .filter_map(|child| Some(snarl.get_node(child)?.node_id.clone()))
+            .collect::<Vec<_>>();
+        if from == to || from >= before.len() || to >= before.len() {
+            return None;
+        }
+
+        let mut after = before.clone();
+        let child = after.remove(from);
+        after.insert(to, child);
+
+        Some(Self::ReorderChildren {
+            node: node.node_id.clone(),
+            output,
+            before,
+            after,
+        })
+    }
+
+    pub fn apply(&self, snarl: &mut Snarl<HyNode>) {
+        match self {
+            EditCommand::AddNode { pos, node } => {
+                snarl.insert_node(*pos, node.clone());
+            }
+            EditCommand::RemoveNodes { nodes, .. } => {
+                for (_, node) in nodes.iter() {
+                    if let Some(id) = find_node(snarl, &node.node_id) {
+                        snarl.remove_node(id);
+                    }
+                }
+            }
+            EditCommand::Connect(wire) => connect(snarl, std::slice::from_ref(wire)),
+            EditCommand::Disconnect(wires) => disconnect(snarl, wires),
+            EditCommand::MoveNodes(moves) => {
+                for (node, _, after) in moves.iter() {
+                    set_position(snarl, node, *after);
+                }
+            }
+            EditCommand::SetValue {
+                node,
+                content,
+                after,
+                ..
+            } => set_value(snarl, node, content, after),
+            EditCommand::ReorderChildren {
+                node,
+                output,
+                after,
+                ..
+            } => set_child_order(snarl, node, output, after),
+        }
+    }
+
+    pub fn revert(&self, snarl: &mut Snarl<HyNode>) {
+        match self {
+            EditCommand::AddNode { node, .. } => {
+                if let Some(id) = find_node(snarl, &node.node_id) {
+                    snarl.remove_node(id);
+                }
+            }
+            EditCommand::RemoveNodes { nodes, wires } => {
+                for (pos, node) in nodes.iter() {
+                    snarl.insert_node(*pos, node.clone());
+                }
+                connect(snarl, wires);
+            }
+            EditCommand::Connect(wire) => disconnect(snarl, std::slice::from_ref(wire)),
+            EditCommand::Disconnect(wires) => connect(snarl, wires),
+            EditCommand::MoveNodes(moves) => {
+                for (node, before, _) in moves.iter() {
+                    set_position(snarl, node, *before);
+                }
+            }
+            EditCommand::SetValue {
+                node,
+                content,
+                before,
+                ..
+            } => set_value(snarl, node, content, before),
+            EditCommand::ReorderChildren {
+                node,
+                output,
+                before,
+                ..
+            } => set_child_order(snarl, node, output, before),
+        }
+    }
+
+    /// Folds a following command into this one if both change the same value or move the same nodes
+    fn merge(&mut self, next: &EditCommand) -> bool {
+        match (self, next) {
+            (
+                EditCommand::SetValue {
+                    node,
+                    content,
+                    after,
+                    ..
+                },
+                EditCommand::SetValue {
+                    node: next_node,
+                    content: next_content,
+                    after: next_after,
+                    ..
+                },
+            ) if node == next_node && content == next_content => {
+                *after = next_after.clone();
+                true
+            }
+            (EditCommand::MoveNodes(moves), EditCommand::MoveNodes(next_moves))
+                if moves.len() == next_moves.len()
+                    && moves.iter().zip(next_moves.iter()).all(|(a, b)| a.0 == b.0) =>
+            {
+                for (current, next) in moves.iter_mut().zip(next_moves.iter()) {
+                    current.2 = next.2;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Undo and redo stacks of a document
+#[derive(Debug, Default)]
+pub struct History {
+    /// Commands with the time they were last extended at
+    undo: Vec<(EditCommand, f64)>,
+    redo: Vec<EditCommand>,
+    /// Incremented on every change, used to detect modifications
+    revision: u64,
+}
+
+impl History {
+    /// Applies the command to the graph and records it
+    pub fn execute(&mut self, snarl: &mut Snarl<HyNode>, command: EditCommand, time: f64) {
+        command.apply(snarl);
+        self.record(command, time);
+    }
+
+    /// Records a command that was already applied to the graph (e.g. by a widget)
+    pub fn record(&mut self, command: EditCommand, time: f64) {
+        self.redo.clear();
+        self.revision += 1;
+
+        if let Some((last, last_time)) = self.undo.last_mut()
+            && time - *last_time < MERGE_WINDOW
+            && last.merge(&command)
+        {
+            *last_time = time;
+            return;
+        }
+
+        self.undo.push((command, time));
+    }
+
+    pub fn undo(&mut self, snarl: &mut Snarl<HyNode>) -> bool {
+        let Some((command, _)) = self.undo.pop() else {
+            return false;
+        };
+        command.revert(snarl);
+        self.redo.push(command);
+        self.revision += 1;
+        true
+    }
+
+    pub fn redo(&mut self, snarl: &mut Snarl<HyNode>) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(snarl);
+        // Never merge a redone command with the next edit
+        self.undo.push((command, f64::NEG_INFINITY));
+        self.revision += 1;
+        true
+    }
+
+    /// Drops all undo and redo steps, the revision is kept so the document stays dirty
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+pub fn find_node(snarl: &Snarl<HyNode>, node_id: &common::NodeId) -> Option<NodeId> {
+    snarl
+        .node_ids()
+        .find(|(_, node)| &node.node_id == node_id)
+        .map(|(id, _)| id)
+}
+
+fn connect(snarl: &mut Snarl<HyNode>, wires: &[Wire]) {
+    for wire in wires.iter() {
+        if let Some((from, to)) = wire.resolve(snarl) {
+            snarl.connect(from, to);
+        }
+    }
+}
+
+fn disconnect(snarl: &mut Snarl<HyNode>, wires: &[Wire]) {
+    for wire in wires.iter() {
+        if let Some((from, to)) = wire.resolve(snarl) {
+            snarl.disconnect(from, to);
+        }
+    }
+}
+
+fn set_position(snarl: &mut Snarl<HyNode>, node_id: &common::NodeId, pos: Pos2) {
+    if let Some(info) = find_node(snarl, node_id).and_then(|id| snarl.get_node_info_mut(id)) {
+        info.pos = pos;
+    }
+}
+
+fn set_value(
+    snarl: &mut Snarl<HyNode>,
+    node_id: &common::NodeId,
+    content: &str,
+    value: &NodeEditorValueTypes,
+) {
+    if let Some(node) = find_node(snarl, node_id).and_then(|id| snarl.get_node_mut(id))
+        && let Some(index) = node
+            .description
+            .content
+            .iter()
+            .position(|c| c.id == content)
+        && let Some((_, current)) = node.values.iter_mut().find(|(i, _)| *i == index)
+    {
+        *current = value.clone();
+    }
+}
+
+fn set_child_order(
+    snarl: &mut Snarl<HyNode>,
+    node_id: &common::NodeId,
+    output: &str,
+    order: &[common::NodeId],
+) {
+    if let Some(node) = find_node(snarl, node_id).and_then(|id| snarl.get_node_mut(id)) {
+        node.child_order.insert(output.to_owned(), order.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Vec2, pos2};
+    use egui_snarl::{InPinId, OutPinId, Snarl};
+    use serde_json::json;
+
+    use crate::{
+        editor::{
+            history::{EditCommand, History, find_node},
+            node::{HyNode, ordered_children},
+            value::NodeEditorValueTypes,
+        },
+        generator::norm::NormalizedNode,
+        workspace::tests::synthetic_workspace,
+    };
+
+    #[test]
+    fn undo_redo_remove_and_edit() {
+        let workspace = synthetic_workspace();
+        let mut snarl = Snarl::new();
+        let biome = snarl.insert_node(
+            pos2(0.0, 0.0),
+            HyNode::new(workspace.get_description("Biome").unwrap().clone()),
+        );
+        let constant = snarl.insert_node(
+            pos2(100.0, 0.0),
+            HyNode::new(
+                workspace
+                    .get_description("ConstantDensity")
+                    .unwrap()
+                    .clone(),
+            ),
+        );
+        let constant_id = snarl[constant].node_id.clone();
+        snarl.connect(
+            OutPinId {
+                node: biome,
+                output: 0,
+            },
+            InPinId {
+                node: constant,
+                input: 0,
+            },
+        );
+
+        let mut history = History::default();
+
+        // Typing in the same field is merged into one step
+        let options = snarl[constant].description.content[0].options.clone();
+        for (time, value) in [(0.0, 1.0), (0.2, 12.0), (0.4, 12.5)] {
+            let before = snarl[constant].values[0].1.clone();
+            let after = NodeEditorValueTypes::from_value(json!(value), &options).unwrap();
+            let command = EditCommand::SetValue {
+                node: constant_id.clone(),
+                content: "Value".to_owned(),
+                before,
+                after,
+            };
+            history.execute(&mut snarl, command, time);
+        }
+
+        let root = snarl[biome].node_id.clone();
+        assert!(EditCommand::remove_nodes(&snarl, &[biome], &root).is_none());
+
+        let command = EditCommand::remove_nodes(&snarl, &[biome, constant], &root).unwrap();
+        history.execute(&mut snarl, command, 1.0);
+        assert_eq!(snarl.nodes().count(), 1);
+        assert_eq!(snarl.wires().count(), 0);
+
+        assert!(history.undo(&mut snarl));
+        let restored = find_node(&snarl, &constant_id).unwrap();
+        assert_eq!(snarl.wires().count(), 1);
+        assert_eq!(snarl[restored].values[0].1.to_value(), json!(12.5));
+
+        assert!(history.undo(&mut snarl));
+        assert_eq!(snarl[restored].values[0].1.to_value(), json!(0.0));
+        assert!(!history.can_undo());
+
+        assert!(history.redo(&mut snarl));
+        assert!(history.redo(&mut snarl));
+        assert!(find_node(&snarl, &constant_id).is_none());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn reorder_children() {
+        let workspace = synthetic_workspace();
+        let constant = workspace.get_description("ConstantDensity").unwrap();
+        let mut snarl = Snarl::new();
+        let sum = snarl.insert_node(
+            pos2(0.0, 0.0),
+            HyNode::new(workspace.get_description("SumDensity").unwrap().clone()),
+        );
+        let first = snarl.insert_node(pos2(200.0, 0.0), HyNode::new(constant.clone()));
+        let second = snarl.insert_node(pos2(200.0, 100.0), HyNode::new(constant.clone()));
+        let pin = OutPinId {
+            node: sum,
+            output: 0,
+        };
+        for child in [second, first] {
+            snarl.connect(
+                pin,
+                InPinId {
+                    node: child,
+                    input: 0,
+                },
+            );
+        }
+
+        // Without a stored order the children are sorted by position
+        assert_eq!(ordered_children(&snarl, pin), [first, second]);
+
+        let mut history = History::default();
+        let command = EditCommand::move_child(&snarl, pin, 1, 0).unwrap();
+        history.execute(&mut snarl, command, 0.0);
+        assert_eq!(ordered_children(&snarl, pin), [second, first]);
+
+        let norm = NormalizedNode::from_editor(&snarl, sum, &workspace, Vec2::ZERO).unwrap();
+        let saved = norm.outputs["Inputs"]
+            .iter()
+            .map(|child| child.node_id.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            saved,
+            [snarl[second].node_id.clone(), snarl[first].node_id.clone()]
+        );
+
+        assert!(history.undo(&mut snarl));
+        assert_eq!(ordered_children(&snarl, pin), [first, second]);
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use egui::Ui;
 
 use crate::{
@@ -8,7 +10,7 @@ use crate::{
 pub fn draw_default_context<'a>(
     ui: &mut Ui,
     groups: &[NodeGroup],
-    descriptors: &'a [NodeDescription],
+    descriptors: &'a [Arc<NodeDescription>],
 ) -> Option<MenuAction<'a>> {
     //let response = ui.allocate_rect(ui.max_rect(), egui::Sense::click_and_drag());
     let mut action = Option::None;
@@ -24,21 +26,21 @@ pub fn draw_default_context<'a>(
         });
     //});
 
-    action.map(|desc| MenuAction::AddNode(desc))
+    action.map(MenuAction::AddNode)
 }
 
 fn draw_group_submenu(
     ui: &mut Ui,
     group: &NodeGroup,
-    descriptors: &[NodeDescription],
+    descriptors: &[Arc<NodeDescription>],
 ) -> Option<usize> {
-    let mut button = ui.add(editor::striped_button::StripedButton::new(
+    let button = ui.add(editor::striped_button::StripedButton::new(
         group.name.clone(),
         group.color.to_egui_color(),
     ));
     let mut action = Option::None;
 
-    egui::containers::menu::SubMenu::default().show(ui, &mut button, |ui| {
+    egui::containers::menu::SubMenu::default().show(ui, &button, |ui| {
         egui::ScrollArea::vertical()
             .max_height(400.0) // Limits the menu height so it doesn't go off-screen
             .show(ui, |ui| {
@@ -59,5 +61,5 @@ fn draw_group_submenu(
             });
     });
 
-    return action;
+    action
 }
@@ -1,12 +1,23 @@
 mod default_context;
 mod node_context;
+mod pin_context;
 
 pub use default_context::draw_default_context;
 pub use node_context::draw_node_context;
+pub use pin_context::draw_pin_context;
+
+use std::sync::Arc;
 
 use crate::workspace::nodes::NodeDescription;
 
 pub enum MenuAction<'a> {
-    AddNode(&'a NodeDescription),
+    AddNode(&'a Arc<NodeDescription>),
     RemoveNode,
+    /// Switches the value at the content index to the raw JSON editor
+    EditAsJson(usize),
+    /// Moves a child of an array output to another index
+    MoveChild {
+        from: usize,
+        to: usize,
+    },
 }
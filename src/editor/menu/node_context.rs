@@ -1,13 +1,39 @@
 use egui::Ui;
 
-use crate::editor::{menu::MenuAction, node::HyNode};
+use crate::editor::{menu::MenuAction, node::HyNode, value::NodeEditorValueTypes};
 
-pub fn draw_node_context<'a>(ui: &mut Ui, node: &HyNode) -> Option<MenuAction<'a>> {
+/// `removable` is false for the root node, which can't be deleted
+pub fn draw_node_context<'a>(
+    ui: &mut Ui,
+    node: &HyNode,
+    removable: bool,
+) -> Option<MenuAction<'a>> {
     let mut action = Option::None;
     egui::ScrollArea::vertical()
         .max_height(800.0) // Limits the menu height so it doesn't go off-screen
         .show(ui, |ui| {
-            if ui.button("Delete").clicked() {
+            // Escape hatch for values the widgets can't edit the way the user wants
+            let editable = node
+                .values
+                .iter()
+                .filter(|(_, value)| !matches!(value, NodeEditorValueTypes::RawJson(_)))
+                .filter_map(|(index, _)| Some((*index, node.description.content.get(*index)?)))
+                .collect::<Vec<_>>();
+            ui.add_enabled_ui(!editable.is_empty(), |ui| {
+                ui.menu_button("Edit as JSON", |ui| {
+                    for (index, content) in editable {
+                        if ui.button(content.options.get_common().0).clicked() {
+                            action = Some(MenuAction::EditAsJson(index));
+                        }
+                    }
+                });
+            });
+
+            if ui
+                .add_enabled(removable, egui::Button::new("Delete"))
+                .on_disabled_hover_text("The root node can't be deleted")
+                .clicked()
+            {
                 action = Some(MenuAction::RemoveNode);
             }
         });
@@ -0,0 +1,46 @@
+use egui::{Button, RichText, Ui};
+
+use crate::editor::menu::MenuAction;
+
+/// Lists the children of an array output in their saved order, with buttons to move them
+pub fn draw_pin_context<'a>(ui: &mut Ui, children: &[String]) -> Option<MenuAction<'a>> {
+    let mut action = Option::None;
+    if children.is_empty() {
+        ui.label(RichText::new("Nothing connected").weak());
+        return action;
+    }
+
+    egui::ScrollArea::vertical()
+        .max_height(800.0) // Limits the menu height so it doesn't go off-screen
+        .show(ui, |ui| {
+            egui::Grid::new("pin_context").show(ui, |ui| {
+                for (index, title) in children.iter().enumerate() {
+                    ui.label(RichText::new(format!("[{}]", index)).weak());
+                    ui.label(title);
+                    if ui
+                        .add_enabled(index > 0, Button::new("⏶"))
+                        .on_hover_text("Move up")
+                        .clicked()
+                    {
+                        action = Some(MenuAction::MoveChild {
+                            from: index,
+                            to: index - 1,
+                        });
+                    }
+                    if ui
+                        .add_enabled(index + 1 < children.len(), Button::new("⏷"))
+                        .on_hover_text("Move down")
+                        .clicked()
+                    {
+                        action = Some(MenuAction::MoveChild {
+                            from: index,
+                            to: index + 1,
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    action
+}
@@ -1,3 +1,4 @@
+pub mod history;
 pub mod menu;
 pub mod node;
 pub mod striped_button;
@@ -5,23 +6,36 @@ pub mod striped_button;
 pub mod value;
 pub mod viewer;
 
+use crate::generator::display_pointer;
+
 #[derive(thiserror::Error, Debug)]
 pub enum EditorError {
-    #[error("Node variant not resolved {0} @ '{1}'")]
-    NodeVariantIndexResolve(usize, String),
     #[error("Numeric value from JSON can't be converted {0}")]
     NumericValueNotParsable(serde_json::Number),
     #[error("Unexpected datatype {0} .. Expected: {1}")]
     UnexpectedDatatype(String, String),
+    #[error("{}: invalid value for '{content}' of {descriptor}: {source}", display_pointer(.path))]
+    InvalidValue {
+        path: String,
+        descriptor: String,
+        content: String,
+        source: Box<EditorError>,
+    },
 }
 
 #[cfg(test)]
 mod test {
-    use std::{env, fs};
+    use std::{env, fs, sync::Arc};
+
+    use serde_json::json;
 
     use crate::{
+        editor::{node::HyNode, value::NodeEditorValueTypes},
         generator::nodes_v1,
-        workspace::{load_descriptions, load_workspace, workspace::Workspace},
+        workspace::{
+            content::ContentType, load_descriptions, load_workspace, tests::synthetic_workspace,
+            workspace::Workspace,
+        },
     };
 
     #[test]
@@ -44,8 +58,235 @@ mod test {
         let node = serde_json::from_str::<nodes_v1::RootNode>(&content).unwrap();
         let norm = node.normalize(&workspace, "Biome").expect("Faile");
 
-        let (_, nodes) = norm.0.to_editor(&workspace);
+        let (_, nodes, _) = norm.0.to_editor(&workspace).unwrap();
 
         println!("{:?}", nodes);
     }
+
+    #[test]
+    fn test_rebind_node() {
+        let old = synthetic_workspace();
+        let new = synthetic_workspace();
+
+        let description = old.get_description("ConstantDensity").unwrap().clone();
+        let mut node = HyNode::new(description);
+        node.values[0].1 =
+            NodeEditorValueTypes::from_value(json!(2.5), &node.description.content[0].options)
+                .unwrap();
+
+        assert!(node.rebind(&new));
+        assert!(Arc::ptr_eq(
+            &node.description,
+            new.get_description("ConstantDensity").unwrap()
+        ));
+        assert_eq!(node.contents().next().unwrap().1.to_value(), json!(2.5));
+
+        // A removed descriptor only flags the node, the values stay for when it comes back
+        let removed = Workspace::construct(
+            new.workspace.clone(),
+            new.nodes
+                .iter()
+                .filter(|desc| desc.id != "ConstantDensity")
+                .map(|desc| desc.as_ref().clone())
+                .collect(),
+        );
+        assert!(!node.rebind(&removed));
+        assert!(node.missing);
+        assert!(node.rebind(&new));
+        assert!(!node.missing);
+        assert_eq!(node.contents().next().unwrap().1.to_value(), json!(2.5));
+    }
+
+    #[test]
+    fn enum_values() {
+        let options = serde_json::from_value::<ContentType>(json!({
+            "Type": "Enum",
+            "Options": { "Label": "Mode", "Values": ["Add", "Max"], "Default": "Add" }
+        }))
+        .unwrap();
+
+        let value = |json| NodeEditorValueTypes::from_value(json, &options).unwrap();
+        assert_eq!(
+            value(json!(null)),
+            NodeEditorValueTypes::Enum(Some("Add".into()))
+        );
+        assert_eq!(value(json!("Max")).to_value(), json!("Max"));
+        // Values outside of the list are kept as they are
+        assert_eq!(value(json!("Min")).to_value(), json!("Min"));
+        assert_eq!(value(json!(3)).to_value(), json!(3));
+    }
+
+    #[test]
+    fn object_values() {
+        let options = serde_json::from_value::<ContentType>(json!({
+            "Type": "Object",
+            "Options": {
+                "Label": "Range",
+                "Fields": [
+                    { "Id": "Min", "Type": "Float", "Options": { "Label": "Min" } },
+                    { "Id": "Scale", "Type": "Object", "Options": {
+                        "Label": "Scale",
+                        "Fields": [{ "Id": "X", "Type": "Int", "Options": { "Label": "X" } }]
+                    } }
+                ]
+            }
+        }))
+        .unwrap();
+
+        let original = json!({ "Custom": [1, 2], "Scale": { "X": 3, "Y": 4 }, "Min": 0.5 });
+        let value = NodeEditorValueTypes::from_value(original.clone(), &options).unwrap();
+        let NodeEditorValueTypes::Object(object) = &value else {
+            panic!("Expected an object value, got {value:?}");
+        };
+        assert_eq!(object.fields.len(), 2);
+        assert_eq!(object.extra.len(), 1);
+
+        // Compared as text, as map equality ignores the key order
+        assert_eq!(value.to_value().to_string(), original.to_string());
+    }
+
+    #[test]
+    fn list_values() {
+        let list = |element: &str| {
+            serde_json::from_value::<ContentType>(json!({
+                "Type": "List",
+                "Options": { "Label": "Values", "ArrayElementType": element }
+            }))
+            .unwrap()
+        };
+
+        let ints = NodeEditorValueTypes::from_value(json!([1, 2]), &list("Int")).unwrap();
+        let NodeEditorValueTypes::List(Some(elements)) = &ints else {
+            panic!("Expected a list value, got {ints:?}");
+        };
+        assert!(matches!(elements[0], NodeEditorValueTypes::IntegerText(_)));
+        assert_eq!(ints.to_value(), json!([1, 2]));
+
+        // Unknown element types are edited as raw JSON
+        let unknown =
+            NodeEditorValueTypes::from_value(json!([{ "A": 1 }]), &list("Vector3")).unwrap();
+        let NodeEditorValueTypes::List(Some(elements)) = &unknown else {
+            panic!("Expected a list value, got {unknown:?}");
+        };
+        assert!(matches!(elements[0], NodeEditorValueTypes::RawJson(_)));
+        assert_eq!(unknown.to_value(), json!([{ "A": 1 }]));
+
+        // A missing list stays missing
+        let missing = NodeEditorValueTypes::from_value(json!(null), &list("Int")).unwrap();
+        assert_eq!(missing.to_value(), json!(null));
+    }
+
+    #[test]
+    fn raw_json_editing() {
+        let value = NodeEditorValueTypes::Enum(Some("Add".into())).to_raw_json();
+        let NodeEditorValueTypes::RawJson(mut json) = value else {
+            panic!("Expected a raw JSON value, got {value:?}");
+        };
+
+        // The value is only replaced once the text parses
+        json.with_content_mut(|text| *text = "{\n  \"Mode\": \n}".to_owned());
+        assert_eq!(json.value(), &json!("Add"));
+        assert_eq!(json.error().map(|error| error.line), Some(3));
+
+        json.with_content_mut(|text| *text = r#"{"Mode":"Max"}"#.to_owned());
+        assert!(json.error().is_none());
+        assert_eq!(json.value(), &json!({ "Mode": "Max" }));
+
+        json.pretty_print();
+        json.with_content_mut(|text| assert_eq!(text, "{\n  \"Mode\": \"Max\"\n}"));
+    }
+
+    #[test]
+    fn to_json_round_trip() {
+        let content = |typ: &str, options| {
+            serde_json::from_value::<ContentType>(json!({ "Type": typ, "Options": options }))
+                .unwrap()
+        };
+        let cases = [
+            (
+                content("SmallString", json!({ "Label": "A" })),
+                json!("Text"),
+            ),
+            (
+                content("Enum", json!({ "Label": "A", "Values": ["Add", "Max"] })),
+                json!("Max"),
+            ),
+            (
+                content("List", json!({ "Label": "A", "ArrayElementType": "Float" })),
+                json!([1.0, 2.5]),
+            ),
+            (
+                content(
+                    "IntSlider",
+                    json!({ "Label": "A", "TickFrequency": 1, "Min": 0, "Max": 10 }),
+                ),
+                json!(4),
+            ),
+            (content("Bool", json!({ "Label": "A" })), json!(true)),
+            (
+                content(
+                    "String",
+                    json!({ "Label": "A", "Width": 100, "Height": 50 }),
+                ),
+                json!("Line\nLine"),
+            ),
+            (content("Checkbox", json!({ "Label": "A" })), json!(false)),
+            (content("Int", json!({ "Label": "A" })), json!(-3)),
+            (content("Float", json!({ "Label": "A" })), json!(2.0)),
+            (
+                content(
+                    "Object",
+                    json!({ "Label": "A", "Fields": [
+                        { "Id": "B", "Type": "Int", "Options": { "Label": "B" } }
+                    ] }),
+                ),
+                json!({ "Other": "kept", "B": 1 }),
+            ),
+            (
+                content("Vector", json!({ "Label": "A" })),
+                json!({ "X": 1, "Y": [2] }),
+            ),
+        ];
+
+        for (typ, original) in cases {
+            let value = NodeEditorValueTypes::from_value(original.clone(), &typ).unwrap();
+            // Compared as text, so integers and floats are told apart
+            assert_eq!(
+                value.to_json(&typ).to_string(),
+                original.to_string(),
+                "{typ:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn to_json_schema() {
+        let int = serde_json::from_value::<ContentType>(
+            json!({ "Type": "Int", "Options": { "Label": "A" } }),
+        )
+        .unwrap();
+        let float = serde_json::from_value::<ContentType>(
+            json!({ "Type": "Float", "Options": { "Label": "A" } }),
+        )
+        .unwrap();
+        let string = serde_json::from_value::<ContentType>(
+            json!({ "Type": "SmallString", "Options": { "Label": "A" } }),
+        )
+        .unwrap();
+
+        let value = |json, typ| NodeEditorValueTypes::from_value(json, typ).unwrap();
+        assert_eq!(value(json!(2.0), &int).to_json(&int).to_string(), "2");
+        assert_eq!(value(json!(1), &float).to_json(&float).to_string(), "1.0");
+
+        // Empty strings are only written if the loaded file had them
+        let empty = value(json!(null), &string);
+        assert_eq!(empty.to_json_entry(&string, false), None);
+        assert_eq!(empty.to_json_entry(&string, true), Some(json!("")));
+
+        // Values not matching their content type are written as they are
+        assert_eq!(
+            value(json!({ "A": 1 }), &string).to_json(&string),
+            json!({ "A": 1 })
+        );
+    }
 }
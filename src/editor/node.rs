@@ -1,14 +1,20 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use derive_where::derive_where;
-use egui::{Color32, Pos2, RichText, TextEdit, Ui};
+use egui::{
+    Color32, Pos2, RichText, TextEdit, Ui,
+    text::{LayoutJob, TextFormat},
+};
+use egui_snarl::{OutPinId, Snarl};
+use indexmap::IndexMap;
 use serde_json::Value;
 
 use crate::{
     editor::{
         EditorError,
-        value::{NodeEditorValueTypes, ValueFilterAction},
+        value::{JsonSyntaxError, NodeEditorValueTypes, NodeJsonEditing, ValueFilterAction},
     },
+    generator::{JsonValue, common::NodeId},
     workspace::{
         self,
         content::{Content, ContentType},
@@ -19,21 +25,44 @@ use crate::{
 
 #[derive(Clone)]
 #[derive_where(Debug)]
-pub struct HyNode<'a> {
+pub struct HyNode {
     pub title: String,
     #[derive_where(skip)]
-    pub description: &'a NodeDescription,
-    pub values: Vec<(&'a Content, NodeEditorValueTypes)>,
+    pub description: Arc<NodeDescription>,
+    pub node_id: NodeId,
+    pub comment: Option<String>,
+    /// Values by index into the `content` of the description
+    pub values: Vec<(usize, NodeEditorValueTypes)>,
+    /// Values from the asset file that are neither content nor pins (e.g. the variant type field)
+    pub extra_values: IndexMap<String, JsonValue>,
+    /// Set if the descriptor disappeared from the workspace during a reload
+    pub missing: bool,
+    /// Order of the children connected to an output by connector id, see [`ordered_children`]
+    pub child_order: HashMap<String, Vec<NodeId>>,
+    /// Order of the keys in the asset file, so saving doesn't reorder them
+    pub key_order: Vec<String>,
+    /// Outputs written as a single object instead of an array in the asset file
+    pub object_outputs: Vec<String>,
+    /// Editor metadata of the node the editor doesn't know (yet)
+    pub metadata: IndexMap<String, JsonValue>,
 }
 
 #[derive(Clone)]
 #[derive_where(Debug)]
-pub struct HyNodeProto<'a> {
+pub struct HyNodeProto {
     pub pos: Pos2,
-    pub variant_index: usize,
     #[derive_where(skip)]
-    pub workspace: &'a Workspace,
+    pub description: Arc<NodeDescription>,
+    pub node_id: NodeId,
+    /// JSON pointer of the node in the asset file
+    pub path: String,
+    pub comment: Option<String>,
     pub values: HashMap<String, NodeEditorValueTypes>,
+    pub extra_values: IndexMap<String, JsonValue>,
+    pub child_order: HashMap<String, Vec<NodeId>>,
+    pub key_order: Vec<String>,
+    pub object_outputs: Vec<String>,
+    pub metadata: IndexMap<String, JsonValue>,
 }
 
 #[derive(Default, Debug)]
@@ -44,17 +73,19 @@ pub struct HyConnection {
     pub to_connector: usize,
 }
 
-impl<'a> HyNode<'a> {
-    pub fn new(description: &'a NodeDescription) -> Self {
+impl HyNode {
+    pub fn new(description: Arc<NodeDescription>) -> Self {
         Self {
             title: description.title.clone(),
-            description,
+            node_id: NodeId::new_rand(&description.id),
+            comment: None,
             values: description
                 .content
                 .iter()
-                .map(|v| {
+                .enumerate()
+                .map(|(index, v)| {
                     (
-                        v,
+                        index,
                         NodeEditorValueTypes::from_value(Value::Null, &v.options).ok(),
                     )
                 })
@@ -66,153 +97,451 @@ impl<'a> HyNode<'a> {
                     }
                 })
                 .collect(),
+            extra_values: IndexMap::new(),
+            missing: false,
+            child_order: HashMap::new(),
+            key_order: Vec::new(),
+            object_outputs: Vec::new(),
+            metadata: IndexMap::new(),
+            description,
+        }
+    }
+
+    /// Iterates over the content descriptions together with their values
+    pub fn contents(&self) -> impl Iterator<Item = (&Content, &NodeEditorValueTypes)> {
+        self.values
+            .iter()
+            .filter_map(|(index, value)| Some((self.description.content.get(*index)?, value)))
+    }
+
+    /// Switches the node to the descriptor with the same id in the given workspace (e.g. after a reload).
+    /// Values are matched by their content id, values without a matching content are kept as extra values.
+    /// Returns false if the workspace has no such descriptor, the node is only flagged as missing in that case
+    pub fn rebind(&mut self, workspace: &Workspace) -> bool {
+        let Some(description) = workspace.get_description(&self.description.id).cloned() else {
+            self.missing = true;
+            return false;
+        };
+        self.missing = false;
+
+        let mut previous = self
+            .values
+            .drain(..)
+            .filter_map(|(index, value)| {
+                Some((self.description.content.get(index)?.id.clone(), value))
+            })
+            .collect::<HashMap<_, _>>();
+
+        self.values = description
+            .content
+            .iter()
+            .enumerate()
+            .filter_map(|(index, content)| {
+                let value = match previous.remove(&content.id) {
+                    Some(value) => value,
+                    None => NodeEditorValueTypes::from_value(Value::Null, &content.options).ok()?,
+                };
+                Some((index, value))
+            })
+            .collect();
+
+        for (key, value) in previous {
+            let value = value.to_value();
+            if !value.is_null() {
+                self.extra_values.entry(key).or_insert(value);
+            }
         }
+
+        self.title = description.title.clone();
+        self.description = description;
+        true
     }
 
-    pub fn draw_content(&mut self, ui: &mut Ui) {
+    /// Draws the editing widgets for all content values.
+    /// Returns the content index and previous value if a value was changed
+    pub fn draw_content(&mut self, ui: &mut Ui) -> Option<(usize, NodeEditorValueTypes)> {
+        let previous = self
+            .values
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>();
+
         egui::containers::Frame::group(ui.style()).show(ui, |ui| {
             ui.vertical(|ui| {
-                for content in self.values.iter_mut() {
-                    let (content_ref, value) = content;
-                    let common = content_ref.options.get_common();
-                    ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
-                    if let Some(width) = common.1 {
-                        ui.set_min_width(width as f32);
-                    }
+                for (index, value) in self.values.iter_mut() {
+                    let Some(content_ref) = self.description.content.get(*index) else {
+                        continue;
+                    };
+                    let id = egui::Id::new((&self.node_id, &content_ref.id));
+                    draw_value(ui, id, &content_ref.options, value);
+                }
+            });
+        });
 
-                    if !matches!(
-                        content_ref.options,
-                        ContentType::Checkbox { .. } | ContentType::Bool { .. }
-                    ) {
-                        ui.strong(common.0);
+        self.values
+            .iter()
+            .zip(previous)
+            .find(|((_, value), previous)| value != previous)
+            .map(|((index, _), previous)| (*index, previous))
+    }
+}
+
+/// Draws the editing widget of a single value, objects draw their fields recursively
+fn draw_value(ui: &mut Ui, id: egui::Id, options: &ContentType, value: &mut NodeEditorValueTypes) {
+    let common = options.get_common();
+    ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+    if let Some(width) = common.1 {
+        ui.set_min_width(width as f32);
+    }
+
+    // List elements have no label, raw JSON is shown instead of a labeled widget
+    let raw = matches!(value, NodeEditorValueTypes::RawJson(_));
+    if !common.0.is_empty()
+        && (raw
+            || !matches!(
+                options,
+                ContentType::Checkbox { .. }
+                    | ContentType::Bool { .. }
+                    | ContentType::Object { .. }
+            ))
+    {
+        ui.strong(common.0);
+    }
+
+    if let NodeEditorValueTypes::RawJson(val) = value {
+        let response = draw_json_editor(ui, val);
+        if let ContentType::Unknown { type_name, .. } = options {
+            if val.error().is_none() {
+                response.on_hover_text(format!("Unknown content type '{type_name}'"));
+            }
+        } else if ui
+            .small_button("Use widget")
+            .on_hover_text("Edit the value with the widget of its content type again")
+            .clicked()
+            && let Ok(widget) = NodeEditorValueTypes::from_value(val.value().clone(), options)
+            && !matches!(widget, NodeEditorValueTypes::RawJson(_))
+        {
+            *value = widget;
+        }
+        return;
+    }
+
+    match options {
+        workspace::content::ContentType::SmallString { .. } => {
+            if let NodeEditorValueTypes::String(val) = value {
+                ui.text_edit_singleline(val);
+            }
+        }
+        workspace::content::ContentType::Enum { values, .. } => {
+            if let NodeEditorValueTypes::Enum(val) = value {
+                let known = val.as_ref().is_none_or(|val| values.contains(val));
+                let selected = match val.as_deref() {
+                    Some(val) if known => RichText::new(val),
+                    Some(val) => {
+                        RichText::new(format!("⚠ {val}")).color(ui.visuals().warn_fg_color)
                     }
+                    None => RichText::new("-"),
+                };
 
-                    match &content_ref.options {
-                        workspace::content::ContentType::SmallString { .. } => {
-                            if let NodeEditorValueTypes::String(val) = value {
-                                ui.text_edit_singleline(val);
-                            }
-                        }
-                        workspace::content::ContentType::Enum { .. } => {
-                            ui.label(RichText::new("JSON only").underline());
+                let response = egui::ComboBox::from_id_salt(id)
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for option in values.iter() {
+                            ui.selectable_value(val, Some(option.clone()), option);
                         }
-                        workspace::content::ContentType::List { .. } => {
-                            ui.label(RichText::new("JSON only").underline());
+                    })
+                    .response;
+                if !known {
+                    response.on_hover_text("Not one of the values of this enum");
+                }
+            }
+        }
+        workspace::content::ContentType::List {
+            array_element_type, ..
+        } => {
+            if let NodeEditorValueTypes::List(elements) = value {
+                draw_list(ui, id, array_element_type, elements);
+            }
+        }
+        workspace::content::ContentType::IntSlider {
+            min,
+            max,
+            tick_frequency,
+            ..
+        } => {
+            if let NodeEditorValueTypes::Integer(val) = value {
+                ui.add(egui::Slider::new(val, *min..=*max).step_by(*tick_frequency as f64));
+            }
+        }
+        workspace::content::ContentType::String { height, .. } => {
+            ui.set_min_height(*height as f32);
+            if let NodeEditorValueTypes::String(val) = value {
+                ui.text_edit_multiline(val);
+            }
+        }
+        workspace::content::ContentType::Checkbox { label, .. }
+        | workspace::content::ContentType::Bool { label, .. } => {
+            if let NodeEditorValueTypes::Boolean(val) = value {
+                ui.checkbox(val, label);
+            }
+        }
+        workspace::content::ContentType::Int { .. } => {
+            if let NodeEditorValueTypes::IntegerText(val) = value {
+                let valid = val.is_valid() && val.is_matching();
+                val.with_content_mut(
+                    |txt| {
+                        let mut edit = TextEdit::singleline(txt);
+                        if !valid {
+                            edit = edit.text_color(Color32::RED);
                         }
-                        workspace::content::ContentType::IntSlider {
-                            min,
-                            max,
-                            tick_frequency,
-                            ..
-                        } => {
-                            if let NodeEditorValueTypes::Integer(val) = value {
-                                ui.add(
-                                    egui::Slider::new(val, *min..=*max)
-                                        .step_by(*tick_frequency as f64),
-                                );
-                            }
+                        edit.show(ui);
+                    },
+                    |_prev, next, res| {
+                        if next.contains('.') || !res.is_some() {
+                            ValueFilterAction::InvalidReset
+                        } else {
+                            ValueFilterAction::Valid
                         }
-                        workspace::content::ContentType::String { height, .. } => {
-                            ui.set_min_height(*height as f32);
-                            if let NodeEditorValueTypes::String(val) = value {
-                                ui.text_edit_multiline(val);
-                            }
-                        }
-                        workspace::content::ContentType::Checkbox { label, .. }
-                        | workspace::content::ContentType::Bool { label, .. } => {
-                            if let NodeEditorValueTypes::Boolean(val) = value {
-                                ui.checkbox(val, label);
-                            }
+                    },
+                );
+            }
+        }
+        workspace::content::ContentType::Float { .. } => {
+            if let NodeEditorValueTypes::FloatText(val) = value {
+                let valid = val.is_valid() && val.is_matching();
+                val.with_content_mut(
+                    |txt| {
+                        let mut edit = TextEdit::singleline(txt);
+                        if !valid {
+                            edit = edit.text_color(Color32::RED);
                         }
-                        workspace::content::ContentType::Int { .. } => {
-                            if let NodeEditorValueTypes::IntegerText(val) = value {
-                                let valid = val.is_valid() && val.is_matching();
-                                val.with_content_mut(
-                                    |txt| {
-                                        let mut edit = TextEdit::singleline(txt);
-                                        if !valid {
-                                            edit = edit.text_color(Color32::RED);
-                                        }
-                                        edit.show(ui);
-                                    },
-                                    |_prev, next, res| {
-                                        if next.contains('.') || !res.is_some() {
-                                            ValueFilterAction::InvalidReset
-                                        } else {
-                                            ValueFilterAction::Valid
-                                        }
-                                    },
-                                );
-                            }
+                        edit.show(ui);
+                    },
+                    |_prev, _next, res| {
+                        if !res.is_some() {
+                            ValueFilterAction::InvalidReset
+                        } else {
+                            ValueFilterAction::Valid
                         }
-                        workspace::content::ContentType::Float { .. } => {
-                            if let NodeEditorValueTypes::FloatText(val) = value {
-                                let valid = val.is_valid() && val.is_matching();
-                                val.with_content_mut(
-                                    |txt| {
-                                        let mut edit = TextEdit::singleline(txt);
-                                        if !valid {
-                                            edit = edit.text_color(Color32::RED);
-                                        }
-                                        edit.show(ui);
-                                    },
-                                    |_prev, _next, res| {
-                                        if !res.is_some() {
-                                            ValueFilterAction::InvalidReset
-                                        } else {
-                                            ValueFilterAction::Valid
-                                        }
-                                    },
-                                );
+                    },
+                );
+            }
+        }
+        workspace::content::ContentType::Object { label, fields } => {
+            if let NodeEditorValueTypes::Object(object) = value {
+                egui::CollapsingHeader::new(RichText::new(label).strong())
+                    .id_salt(id)
+                    .show(ui, |ui| {
+                        for (key, value) in object.fields.iter_mut() {
+                            if let Some(field) = fields.iter().find(|field| field.id == *key) {
+                                draw_value(ui, id.with(key.as_str()), &field.options, value);
                             }
                         }
-                        workspace::content::ContentType::Object { .. } => {
-                            ui.label(RichText::new("JSON only").underline());
+
+                        if !object.extra.is_empty() {
+                            let keys = object.extra.keys().cloned().collect::<Vec<_>>();
+                            ui.weak(format!("{} other keys are kept", keys.len()))
+                                .on_hover_text(keys.join(", "));
                         }
-                    }
-                }
-            });
+                    });
+            }
+        }
+        // Always edited as raw JSON, see above
+        workspace::content::ContentType::Unknown { .. } => {}
+    }
+}
+
+/// Multiline JSON editor which highlights the line of a syntax error and can pretty print the text
+fn draw_json_editor(ui: &mut Ui, val: &mut NodeJsonEditing) -> egui::Response {
+    let error = val.error().cloned();
+    let response = val.with_content_mut(|txt| {
+        let mut layouter = |ui: &Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+            let mut job = json_layout_job(ui, text.as_str(), error.as_ref());
+            job.wrap.max_width = wrap_width;
+            ui.fonts_mut(|fonts| fonts.layout_job(job))
+        };
+        TextEdit::multiline(txt)
+            .code_editor()
+            .desired_rows(1)
+            .layouter(&mut layouter)
+            .show(ui)
+            .response
+    });
+
+    if let Some(error) = val.error() {
+        ui.colored_label(ui.visuals().error_fg_color, error.message.as_str());
+    } else if ui.small_button("Format").clicked() {
+        val.pretty_print();
+    }
+
+    response
+}
+
+/// Lays out JSON text with the line of the syntax error highlighted
+fn json_layout_job(ui: &Ui, text: &str, error: Option<&JsonSyntaxError>) -> LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let normal = TextFormat::simple(font_id.clone(), ui.visuals().text_color());
+    let Some(error) = error else {
+        return LayoutJob::single_section(text.to_owned(), normal);
+    };
+
+    // Byte range of the line, serde_json counts lines from 1
+    let start = text
+        .split_inclusive('\n')
+        .take(error.line.saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>()
+        .min(text.len());
+    let end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |end| start + end);
+
+    let mut job = LayoutJob::default();
+    job.append(&text[..start], 0.0, normal.clone());
+    job.append(
+        &text[start..end],
+        0.0,
+        TextFormat {
+            color: ui.visuals().error_fg_color,
+            background: ui.visuals().error_fg_color.gamma_multiply(0.2),
+            ..normal.clone()
+        },
+    );
+    job.append(&text[end..], 0.0, normal);
+    job
+}
+
+/// Changes to a list, applied after all elements are drawn
+enum ListAction {
+    Remove(usize),
+    Duplicate(usize),
+    Move { from: usize, to: usize },
+}
+
+/// Draws the elements of a list with a drag handle and buttons to duplicate and remove each element
+fn draw_list(
+    ui: &mut Ui,
+    id: egui::Id,
+    element_type: &str,
+    elements: &mut Option<Vec<NodeEditorValueTypes>>,
+) {
+    let element = ContentType::list_element(element_type);
+    let mut action = None;
+
+    for (index, value) in elements.iter_mut().flatten().enumerate() {
+        let row = ui.horizontal(|ui| {
+            // The payload includes the list id, so elements can't be dropped into other lists
+            ui.dnd_drag_source(id.with(index), (id, index), |ui| ui.label("☰"))
+                .response
+                .on_hover_text("Drag to reorder");
+            draw_value(ui, id.with(index), &element, value);
+            if ui.small_button("⧉").on_hover_text("Duplicate").clicked() {
+                action = Some(ListAction::Duplicate(index));
+            }
+            if ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                action = Some(ListAction::Remove(index));
+            }
         });
+
+        if let Some(payload) = row.response.dnd_release_payload::<(egui::Id, usize)>()
+            && payload.0 == id
+        {
+            action = Some(ListAction::Move {
+                from: payload.1,
+                to: index,
+            });
+        }
+    }
+
+    if ui.small_button("+ Add").clicked() {
+        // A missing list is only created once an element is added
+        elements
+            .get_or_insert_default()
+            .push(NodeEditorValueTypes::from_value(Value::Null, &element).unwrap_or_default());
+    }
+
+    let Some(list) = elements else {
+        return;
+    };
+    match action {
+        Some(ListAction::Remove(index)) => {
+            list.remove(index);
+        }
+        Some(ListAction::Duplicate(index)) => list.insert(index + 1, list[index].clone()),
+        Some(ListAction::Move { from, to }) if from != to && from < list.len() => {
+            let value = list.remove(from);
+            list.insert(to.min(list.len()), value);
+        }
+        _ => {}
     }
 }
 
-impl<'a> TryFrom<HyNodeProto<'a>> for HyNode<'a> {
+impl TryFrom<HyNodeProto> for HyNode {
     type Error = EditorError;
 
-    fn try_from(mut value: HyNodeProto<'a>) -> Result<Self, Self::Error> {
-        let desc = value
-            .workspace
-            .nodes
-            .get(value.variant_index)
-            .ok_or_else(|| {
-                EditorError::NodeVariantIndexResolve(
-                    value.variant_index,
-                    value.workspace.workspace.workspace_name.clone(),
-                )
-            })?;
-
-        let values = desc
-            .content
-            .iter()
-            .map(|content| {
-                value
-                    .values
-                    .remove(&content.id)
-                    .map(|v| (content, v))
-                    .unwrap_or_else(|| {
-                        (
-                            content,
-                            NodeEditorValueTypes::from_value(Value::Null, &content.options)
-                                .unwrap(),
-                        )
-                    })
-            })
-            .collect::<Vec<_>>();
+    fn try_from(mut value: HyNodeProto) -> Result<Self, Self::Error> {
+        let desc = value.description;
+
+        let mut values = Vec::with_capacity(desc.content.len());
+        for (index, content) in desc.content.iter().enumerate() {
+            let content_value = match value.values.remove(&content.id) {
+                Some(content_value) => content_value,
+                None => NodeEditorValueTypes::from_value(Value::Null, &content.options).map_err(
+                    |source| EditorError::InvalidValue {
+                        path: value.path.clone(),
+                        descriptor: desc.id.clone(),
+                        content: content.id.clone(),
+                        source: Box::new(source),
+                    },
+                )?,
+            };
+            values.push((index, content_value));
+        }
 
         Ok(Self {
             title: desc.title.to_string(),
             description: desc,
+            node_id: value.node_id,
+            comment: value.comment,
             values,
+            extra_values: value.extra_values,
+            missing: false,
+            child_order: value.child_order,
+            key_order: value.key_order,
+            object_outputs: value.object_outputs,
+            metadata: value.metadata,
         })
     }
 }
+
+/// The nodes connected to an output in the order they are saved in.
+/// Children without a stored order (e.g. newly connected ones) follow, sorted from top to bottom
+pub fn ordered_children(snarl: &Snarl<HyNode>, pin: OutPinId) -> Vec<egui_snarl::NodeId> {
+    let node = &snarl[pin.node];
+    let order = node
+        .description
+        .outputs
+        .get(pin.output)
+        .and_then(|connector| node.child_order.get(&connector.id));
+
+    let mut children = snarl
+        .out_pin(pin)
+        .remotes
+        .iter()
+        .filter_map(|remote| {
+            let info = snarl.get_node_info(remote.node)?;
+            let index = order
+                .and_then(|order| order.iter().position(|id| *id == info.value.node_id))
+                .unwrap_or(usize::MAX);
+            Some((index, info.pos, remote.node))
+        })
+        .collect::<Vec<_>>();
+    children.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(a.1.y.total_cmp(&b.1.y))
+            .then(a.1.x.total_cmp(&b.1.x))
+    });
+    children.dedup_by_key(|child| child.2);
+
+    children.into_iter().map(|(_, _, id)| id).collect()
+}
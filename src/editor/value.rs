@@ -1,26 +1,88 @@
 use std::{fmt::Debug, str::FromStr};
 
 use crate::{
-    editor::EditorError, generator::JsonValue, workspace::content::{ContentType, ValueType}
+    editor::EditorError,
+    generator::JsonValue,
+    workspace::content::{ContentObjectFields, ContentType, ValueType},
 };
 
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum NodeEditorValueTypes {
     #[default]
     Null,
-    Other(serde_json::Value),
     String(String),
     Integer(i64),
     IntegerText(NodeNumericEditing<i64>),
     Float(i64),
     FloatText(NodeNumericEditing<f64>),
     Boolean(bool),
+    /// Selected value of an enum, which may be missing from its value list if it came from an asset
+    Enum(Option<String>),
+    Object(NodeObjectValue),
+    /// Elements of a list, `None` if the list is missing in the asset
+    List(Option<Vec<NodeEditorValueTypes>>),
+    /// Any value the other types can't represent, edited as JSON text
+    RawJson(NodeJsonEditing),
+}
+
+/// Values of an object content, keys without a field in the description are kept as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeObjectValue {
+    /// Values by field id
+    pub fields: Vec<(String, NodeEditorValueTypes)>,
+    pub extra: serde_json::Map<String, JsonValue>,
+    key_order: Vec<String>,
+}
+
+/// Free form JSON text, only replacing the value once the text parses
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeJsonEditing {
+    current_value: serde_json::Value,
+    text_field: String,
+    error: Option<JsonSyntaxError>,
+}
+
+/// Why the text of a [`NodeJsonEditing`] doesn't parse, with the 1-based position of the error
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonSyntaxError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The integer of a JSON number, None for other types and numbers with a fractional part
+fn as_integer(value: &JsonValue) -> Option<i64> {
+    value.as_i64().or_else(|| {
+        value
+            .as_f64()
+            .filter(|value| {
+                value.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(value)
+            })
+            .map(|value| value as i64)
+    })
+}
+
+/// Numbers that can be written to JSON, which has no infinity or NaN
+pub trait Finite {
+    fn is_finite(&self) -> bool;
+}
+
+impl Finite for i64 {
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+impl Finite for f64 {
+    fn is_finite(&self) -> bool {
+        f64::is_finite(*self)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct NodeNumericEditing<T>
 where
-    T: FromStr + ToString + PartialOrd + Debug + Copy + Sized,
+    T: FromStr + ToString + PartialOrd + Debug + Copy + Sized + Finite,
 {
     current_value: T,
     text_field: String,
@@ -40,64 +102,323 @@ impl NodeEditorValueTypes {
     pub fn from_value(value: JsonValue, typ: &ContentType) -> Result<Self, EditorError> {
         let (default, real_type) = typ.get_default();
         Ok(match real_type {
-            ValueType::Boolean => NodeEditorValueTypes::Boolean(
-                value
-                    .as_bool()
-                    .or_else(|| default.as_bool())
-                    .unwrap_or(!value.is_null()),
-            ),
-            ValueType::Float => {
-                if let ContentType::Float { min, max, .. } = typ {
+            // Values of another JSON type are kept as raw JSON, so saving doesn't change them
+            ValueType::Boolean => match value {
+                JsonValue::Null => {
+                    NodeEditorValueTypes::Boolean(default.as_bool().unwrap_or(false))
+                }
+                JsonValue::Bool(value) => NodeEditorValueTypes::Boolean(value),
+                other => NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other)),
+            },
+            ValueType::Float => match (value, typ) {
+                (JsonValue::Null, ContentType::Float { min, max, .. }) => {
                     NodeEditorValueTypes::FloatText(NodeNumericEditing::new(
-                        value.as_f64().or_else(|| default.as_f64()).unwrap_or(0.0),
+                        default.as_f64().unwrap_or(0.0),
                         *min,
                         *max,
                     ))
-                } else {
-                    unreachable!()
                 }
-            }
-            ValueType::Int => {
-                if let ContentType::Int { min, max, .. } = typ {
-                    NodeEditorValueTypes::IntegerText(NodeNumericEditing::new(
-                        value
-                            .as_i64()
-                            .or_else(|| value.as_f64().map(|v| v as i64))
-                            .or_else(|| default.as_i64())
-                            .or_else(|| default.as_f64().map(|v| v as i64))
-                            .unwrap_or(0),
+                (JsonValue::Number(number), ContentType::Float { min, max, .. }) => {
+                    NodeEditorValueTypes::FloatText(NodeNumericEditing::new(
+                        number.as_f64().unwrap_or(0.0),
                         *min,
                         *max,
                     ))
-                } else if let ContentType::IntSlider { .. } = typ {
-                    NodeEditorValueTypes::Integer(
-                        value
-                            .as_i64()
-                            .or_else(|| value.as_f64().map(|v| v as i64))
-                            .or_else(|| default.as_i64())
-                            .or_else(|| default.as_f64().map(|v| v as i64))
-                            .unwrap_or(0),
-                    )
+                }
+                (other, ContentType::Float { .. }) => {
+                    NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other))
+                }
+                _ => unreachable!(),
+            },
+            ValueType::Int => {
+                let integer = if value.is_null() {
+                    Some(as_integer(&default).unwrap_or(0))
                 } else {
-                    unreachable!()
+                    as_integer(&value)
+                };
+                match (integer, typ) {
+                    (Some(integer), ContentType::Int { min, max, .. }) => {
+                        NodeEditorValueTypes::IntegerText(NodeNumericEditing::new(
+                            integer, *min, *max,
+                        ))
+                    }
+                    (Some(integer), ContentType::IntSlider { .. }) => {
+                        NodeEditorValueTypes::Integer(integer)
+                    }
+                    (None, ContentType::Int { .. } | ContentType::IntSlider { .. }) => {
+                        NodeEditorValueTypes::RawJson(NodeJsonEditing::new(value))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            ValueType::Object => match (value, typ) {
+                (JsonValue::Object(object), ContentType::Object { fields, .. }) => {
+                    NodeEditorValueTypes::Object(NodeObjectValue::new(object, fields)?)
+                }
+                (JsonValue::Null, ContentType::Object { fields, .. }) => {
+                    NodeEditorValueTypes::Object(NodeObjectValue::new(Default::default(), fields)?)
+                }
+                (other, _) => NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other)),
+            },
+            ValueType::String => match value {
+                JsonValue::Null => NodeEditorValueTypes::String(String::new()),
+                JsonValue::String(value) => NodeEditorValueTypes::String(value),
+                other => NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other)),
+            },
+            ValueType::List => match (value, typ) {
+                (JsonValue::Null, _) => NodeEditorValueTypes::List(None),
+                (
+                    JsonValue::Array(elements),
+                    ContentType::List {
+                        array_element_type, ..
+                    },
+                ) => {
+                    let element = ContentType::list_element(array_element_type);
+                    NodeEditorValueTypes::List(Some(
+                        elements
+                            .into_iter()
+                            .map(|value| NodeEditorValueTypes::from_value(value, &element))
+                            .collect::<Result<_, _>>()?,
+                    ))
+                }
+                (other, _) => NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other)),
+            },
+            ValueType::Enum => match value {
+                JsonValue::Null => {
+                    NodeEditorValueTypes::Enum(default.as_str().map(|v| v.to_string()))
                 }
+                JsonValue::String(value) => NodeEditorValueTypes::Enum(Some(value)),
+                other => NodeEditorValueTypes::RawJson(NodeJsonEditing::new(other)),
+            },
+            ValueType::Unknown => {
+                NodeEditorValueTypes::RawJson(NodeJsonEditing::new(if value.is_null() {
+                    default
+                } else {
+                    value
+                }))
             }
-            ValueType::Object => NodeEditorValueTypes::Other(value),
-            ValueType::String => NodeEditorValueTypes::String(
-                value
-                    .as_str()
-                    .map(|v| v.to_string())
-                    .unwrap_or_else(String::new),
-            ),
-            ValueType::List => NodeEditorValueTypes::Other(value),
-            ValueType::Enum => NodeEditorValueTypes::Other(value),
         })
     }
+
+    /// The same value as raw JSON, so any value can be edited as text
+    pub fn to_raw_json(&self) -> Self {
+        NodeEditorValueTypes::RawJson(NodeJsonEditing::new(self.to_value()))
+    }
+
+    /// Converts the value into the JSON the content type expects (e.g. integers for `Int`, floats for `Float`).
+    /// Empty strings count as unset and become `Null`, raw JSON and mismatching values are kept verbatim
+    pub fn to_json(&self, typ: &ContentType) -> JsonValue {
+        match (self, typ) {
+            (
+                NodeEditorValueTypes::Integer(_) | NodeEditorValueTypes::IntegerText(_),
+                ContentType::Int { .. } | ContentType::IntSlider { .. },
+            ) => self.to_value(),
+            (
+                NodeEditorValueTypes::FloatText(value),
+                ContentType::Int { .. } | ContentType::IntSlider { .. },
+            ) => JsonValue::from(value.value().round() as i64),
+            (NodeEditorValueTypes::FloatText(value), ContentType::Float { .. }) => {
+                JsonValue::from(value.value())
+            }
+            (NodeEditorValueTypes::Integer(value), ContentType::Float { .. }) => {
+                JsonValue::from(*value as f64)
+            }
+            (NodeEditorValueTypes::IntegerText(value), ContentType::Float { .. }) => {
+                JsonValue::from(value.value() as f64)
+            }
+            (
+                NodeEditorValueTypes::String(value),
+                ContentType::SmallString { .. } | ContentType::String { .. },
+            ) if value.is_empty() => JsonValue::Null,
+            (
+                NodeEditorValueTypes::List(Some(elements)),
+                ContentType::List {
+                    array_element_type, ..
+                },
+            ) => {
+                let element = ContentType::list_element(array_element_type);
+                elements
+                    .iter()
+                    .map(|value| value.to_json(&element))
+                    .collect()
+            }
+            (NodeEditorValueTypes::Object(object), ContentType::Object { fields, .. }) => {
+                object.to_json(fields)
+            }
+            _ => self.to_value(),
+        }
+    }
+
+    /// [`Self::to_json`] for a key of an object, `None` if the key should be left out.
+    /// `present` tells if the key was in the loaded file. If it wasn't, the key stays out as long as
+    /// the value is the one a missing key loads as, and empty strings are only kept if it was
+    pub fn to_json_entry(&self, typ: &ContentType, present: bool) -> Option<JsonValue> {
+        match self.to_json(typ) {
+            JsonValue::Null
+                if present
+                    && matches!(self, NodeEditorValueTypes::String(value) if value.is_empty()) =>
+            {
+                Some(JsonValue::from(""))
+            }
+            JsonValue::Null => None,
+            json if !present
+                && NodeEditorValueTypes::from_value(JsonValue::Null, typ)
+                    .is_ok_and(|default| default.to_json(typ) == json) =>
+            {
+                None
+            }
+            json => Some(json),
+        }
+    }
+
+    /// Converts the editor value back into the JSON representation used by the asset files
+    pub fn to_value(&self) -> JsonValue {
+        match self {
+            NodeEditorValueTypes::Null => JsonValue::Null,
+            NodeEditorValueTypes::String(value) => JsonValue::from(value.as_str()),
+            NodeEditorValueTypes::Integer(value) => JsonValue::from(*value),
+            NodeEditorValueTypes::IntegerText(value) => JsonValue::from(value.value()),
+            NodeEditorValueTypes::Float(value) => JsonValue::from(*value),
+            NodeEditorValueTypes::FloatText(value) => JsonValue::from(value.value()),
+            NodeEditorValueTypes::Boolean(value) => JsonValue::from(*value),
+            NodeEditorValueTypes::Enum(value) => value
+                .as_deref()
+                .map(JsonValue::from)
+                .unwrap_or(JsonValue::Null),
+            NodeEditorValueTypes::Object(value) => value.to_value(),
+            NodeEditorValueTypes::List(elements) => match elements {
+                Some(elements) => elements.iter().map(Self::to_value).collect(),
+                None => JsonValue::Null,
+            },
+            NodeEditorValueTypes::RawJson(value) => value.value().clone(),
+        }
+    }
+}
+
+impl NodeObjectValue {
+    pub fn new(
+        mut object: serde_json::Map<String, JsonValue>,
+        fields: &[ContentObjectFields],
+    ) -> Result<Self, EditorError> {
+        let key_order = object.keys().cloned().collect();
+        let fields = fields
+            .iter()
+            .map(|field| {
+                let value = object.remove(&field.id).unwrap_or_default();
+                let value = NodeEditorValueTypes::from_value(value, &field.options)?;
+                Ok((field.id.clone(), value))
+            })
+            .collect::<Result<_, EditorError>>()?;
+
+        Ok(Self {
+            fields,
+            extra: object,
+            key_order,
+        })
+    }
+
+    /// Keys keep the position they had when loaded, new ones are appended
+    pub fn to_value(&self) -> JsonValue {
+        self.collect(
+            self.fields
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_value()))
+                .filter(|(_, value)| !value.is_null())
+                .collect(),
+        )
+    }
+
+    /// [`Self::to_value`] with every field converted by [`NodeEditorValueTypes::to_json`]
+    pub fn to_json(&self, fields: &[ContentObjectFields]) -> JsonValue {
+        self.collect(
+            self.fields
+                .iter()
+                .filter_map(|(key, value)| {
+                    let json = match fields.iter().find(|field| field.id == *key) {
+                        Some(field) => {
+                            value.to_json_entry(&field.options, self.key_order.contains(key))?
+                        }
+                        None => value.to_value(),
+                    };
+                    (!json.is_null()).then(|| (key.clone(), json))
+                })
+                .collect(),
+        )
+    }
+
+    /// Adds the extra keys to the fields and restores the loaded key order
+    fn collect(&self, fields: Vec<(String, JsonValue)>) -> JsonValue {
+        let mut entries = fields
+            .into_iter()
+            .chain(self.extra.clone())
+            .collect::<Vec<_>>();
+        entries.sort_by_cached_key(|(key, _)| {
+            self.key_order
+                .iter()
+                .position(|k| k == key)
+                .unwrap_or(usize::MAX)
+        });
+
+        JsonValue::Object(entries.into_iter().collect())
+    }
+}
+
+impl NodeJsonEditing {
+    pub fn new(value: JsonValue) -> Self {
+        Self {
+            text_field: serde_json::to_string_pretty(&value).unwrap_or_default(),
+            current_value: value,
+            error: None,
+        }
+    }
+
+    /// Edits the text, updating the value if the new text is valid JSON
+    pub fn with_content_mut<F, R>(&mut self, cl: F) -> R
+    where
+        F: FnOnce(&mut String) -> R,
+    {
+        let previous = self.text_field.clone();
+        let result = cl(&mut self.text_field);
+
+        if previous != self.text_field {
+            match serde_json::from_str(&self.text_field) {
+                Ok(value) => {
+                    self.current_value = value;
+                    self.error = None;
+                }
+                Err(err) => {
+                    self.error = Some(JsonSyntaxError {
+                        message: err.to_string(),
+                        line: err.line(),
+                        column: err.column(),
+                    })
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn value(&self) -> &JsonValue {
+        &self.current_value
+    }
+
+    /// The parse error of the current text, if any
+    pub fn error(&self) -> Option<&JsonSyntaxError> {
+        self.error.as_ref()
+    }
+
+    /// Replaces the text with the pretty printed value, unless the text doesn't parse
+    pub fn pretty_print(&mut self) {
+        if self.error.is_none() {
+            self.text_field = serde_json::to_string_pretty(&self.current_value).unwrap_or_default();
+        }
+    }
 }
 
 impl<T> NodeNumericEditing<T>
 where
-    T: FromStr + ToString + PartialOrd + Debug + Copy + Sized,
+    T: FromStr + ToString + PartialOrd + Debug + Copy + Sized + Finite,
 {
     pub fn new(value: T, min: Option<T>, max: Option<T>) -> Self {
         Self {
@@ -160,6 +481,10 @@ where
         self.is_valid()
     }
 
+    pub fn value(&self) -> T {
+        self.current_value
+    }
+
     pub fn is_matching(&self) -> bool {
         self.matching
     }
@@ -169,7 +494,9 @@ where
     }
 
     fn is_valid_impl(&self, value: &T) -> bool {
-        if let Some(min) = self.min
+        if !value.is_finite() {
+            false
+        } else if let Some(min) = self.min
             && min > *value
         {
             false
@@ -182,3 +509,65 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{NodeEditorValueTypes, NodeNumericEditing, ValueFilterAction};
+    use crate::{generator::JsonValue, workspace::content::ContentType};
+
+    #[test]
+    pub fn non_finite_floats_rejected() {
+        let mut editing = NodeNumericEditing::new(1.5f64, None, None);
+        for text in ["inf", "-inf", "NaN"] {
+            editing.with_content_mut(
+                |field| *field = text.to_owned(),
+                |_, _, _| ValueFilterAction::Valid,
+            );
+            assert_eq!(editing.value(), 1.5);
+        }
+        assert!(!editing.set_value(f64::INFINITY));
+        assert!(editing.set_value(-2.0));
+    }
+
+    #[test]
+    pub fn mismatching_types_kept_as_raw_json() {
+        let int = ContentType::Int {
+            label: "Seed".to_owned(),
+            width: None,
+            default: None,
+            min: None,
+            max: None,
+        };
+        let float = ContentType::Float {
+            label: "Scale".to_owned(),
+            width: None,
+            default: None,
+            min: None,
+            max: None,
+        };
+        let checkbox = ContentType::Checkbox {
+            label: "Enabled".to_owned(),
+            width: None,
+            default: None,
+        };
+
+        for (value, typ) in [
+            (json!(2.7), &int),
+            (json!("3"), &int),
+            (json!("1.5"), &float),
+            (json!("yes"), &checkbox),
+            (json!(1), &checkbox),
+        ] {
+            let parsed = NodeEditorValueTypes::from_value(value.clone(), typ).unwrap();
+            assert!(matches!(parsed, NodeEditorValueTypes::RawJson(_)));
+            assert_eq!(parsed.to_json(typ), value);
+        }
+
+        let whole = NodeEditorValueTypes::from_value(json!(3.0), &int).unwrap();
+        assert_eq!(whole.to_json(&int), json!(3));
+        let unset = NodeEditorValueTypes::from_value(JsonValue::Null, &checkbox).unwrap();
+        assert_eq!(unset.to_json(&checkbox), json!(false));
+    }
+}
@@ -1,28 +1,54 @@
-use std::usize;
-
-use egui::{RichText, Ui};
+use egui::{RichText, Sense, Ui};
 use egui_snarl::{
     InPin, NodeId, OutPin, Snarl,
     ui::{PinInfo, SnarlViewer},
 };
 
 use crate::{
-    editor::{menu::MenuAction, node::HyNode},
+    editor::{
+        history::{EditCommand, History, Wire},
+        menu::MenuAction,
+        node::{HyNode, ordered_children},
+    },
+    generator::common,
     workspace::workspace::Workspace,
 };
 
 pub struct HyNodeViewer<'a> {
     pub workspace: &'a Workspace,
+    /// Asset node id of the root node of the document, which can't be removed
+    pub root: &'a common::NodeId,
+    /// Every modification of the graph is recorded here
+    pub history: &'a mut History,
+    /// Current input time, used to merge consecutive edits
+    pub time: f64,
 }
 
-impl<'a, 'b> SnarlViewer<HyNode<'b>> for HyNodeViewer<'a>
-where
-    'a: 'b,
-{
+impl<'a> SnarlViewer<HyNode> for HyNodeViewer<'a> {
     fn title(&mut self, node: &HyNode) -> String {
         node.title.to_owned()
     }
 
+    fn show_header(
+        &mut self,
+        node: NodeId,
+        _inputs: &[InPin],
+        _outputs: &[OutPin],
+        ui: &mut Ui,
+        snarl: &mut Snarl<HyNode>,
+    ) {
+        let node = &snarl[node];
+        if node.missing {
+            ui.label(RichText::new(format!("⚠ {}", node.title)).color(ui.visuals().warn_fg_color))
+                .on_hover_text(format!(
+                    "The descriptor {} no longer exists in the workspace",
+                    node.description.id
+                ));
+        } else {
+            ui.label(self.title(node));
+        }
+    }
+
     fn inputs(&mut self, node: &HyNode) -> usize {
         node.description.inputs.len()
     }
@@ -30,35 +56,55 @@ where
     fn show_node_menu(
         &mut self,
         node: NodeId,
-        inputs: &[InPin],
-        outputs: &[OutPin],
+        _inputs: &[InPin],
+        _outputs: &[OutPin],
         ui: &mut Ui,
         snarl: &mut Snarl<HyNode>,
     ) {
-        if let Some(node_ref) = snarl.get_node(node) {
-            match super::menu::draw_node_context(ui, node_ref) {
+        let Some(node_ref) = snarl.get_node(node) else {
+            return;
+        };
+
+        let command =
+            match super::menu::draw_node_context(ui, node_ref, node_ref.node_id != *self.root) {
                 Some(MenuAction::RemoveNode) => {
-                    snarl.remove_node(node);
+                    EditCommand::remove_nodes(snarl, &[node], self.root)
                 }
-                _ => {}
-            }
+                Some(MenuAction::EditAsJson(index)) => node_ref
+                    .values
+                    .iter()
+                    .find(|(i, _)| *i == index)
+                    .zip(node_ref.description.content.get(index))
+                    .map(|((_, value), content)| EditCommand::SetValue {
+                        node: node_ref.node_id.clone(),
+                        content: content.id.clone(),
+                        before: value.clone(),
+                        after: value.to_raw_json(),
+                    }),
+                _ => None,
+            };
+        if let Some(command) = command {
+            self.history.execute(snarl, command, self.time);
         }
     }
 
-    fn has_node_menu(&mut self, node: &HyNode) -> bool {
+    fn has_node_menu(&mut self, _node: &HyNode) -> bool {
         true
     }
 
-    fn has_graph_menu(&mut self, pos: egui::Pos2, snarl: &mut Snarl<HyNode>) -> bool {
+    fn has_graph_menu(&mut self, _pos: egui::Pos2, _snarl: &mut Snarl<HyNode>) -> bool {
         true
     }
 
-    fn show_graph_menu(&mut self, pos: egui::Pos2, ui: &mut Ui, snarl: &mut Snarl<HyNode<'b>>) {
-        match super::menu::draw_default_context(ui, &self.workspace.groups, &self.workspace.nodes) {
-            Some(MenuAction::AddNode(descriptor)) => {
-                snarl.insert_node(pos, HyNode::new(descriptor));
-            }
-            _ => {}
+    fn show_graph_menu(&mut self, pos: egui::Pos2, ui: &mut Ui, snarl: &mut Snarl<HyNode>) {
+        if let Some(MenuAction::AddNode(descriptor)) =
+            super::menu::draw_default_context(ui, &self.workspace.groups, &self.workspace.nodes)
+        {
+            let command = EditCommand::AddNode {
+                pos,
+                node: HyNode::new(descriptor.clone()),
+            };
+            self.history.execute(snarl, command, self.time);
         }
     }
 
@@ -69,9 +115,26 @@ where
         ui: &mut egui::Ui,
         snarl: &mut egui_snarl::Snarl<HyNode>,
     ) -> PinInfo {
-        let pin = &snarl[pin.id.node].description.inputs[pin.id.input];
-        ui.label(&pin.label);
-        return PinInfo::circle().with_fill(pin.color.into());
+        // Children of array outputs show their index in the array
+        let index = pin.remotes.iter().find_map(|remote| {
+            let parent = snarl[remote.node].description.outputs.get(remote.output)?;
+            if !parent.multiple {
+                return None;
+            }
+            ordered_children(snarl, *remote)
+                .iter()
+                .position(|child| *child == pin.id.node)
+        });
+
+        // The pin can be gone for a frame after a reload changed the description
+        let Some(connector) = snarl[pin.id.node].description.inputs.get(pin.id.input) else {
+            return PinInfo::default();
+        };
+        match index {
+            Some(index) => ui.label(format!("{} [{}]", connector.label, index)),
+            None => ui.label(&connector.label),
+        };
+        PinInfo::circle().with_fill(connector.color.into())
     }
 
     fn outputs(&mut self, node: &HyNode) -> usize {
@@ -85,41 +148,97 @@ where
         ui: &mut egui::Ui,
         snarl: &mut egui_snarl::Snarl<HyNode>,
     ) -> PinInfo {
-        let pin = &snarl[pin.id.node].description.outputs[pin.id.output];
-        if !pin.multiple {
-            ui.label(&pin.label);
-        } else {
-            ui.label(RichText::new(&pin.label).italics());
+        let Some(connector) = snarl[pin.id.node].description.outputs.get(pin.id.output) else {
+            return PinInfo::default();
+        };
+        let color = connector.color;
+        if !connector.multiple {
+            ui.label(&connector.label);
+            return PinInfo::circle().with_fill(color.into());
+        }
+
+        let response = ui
+            .add(egui::Label::new(RichText::new(&connector.label).italics()).sense(Sense::click()))
+            .on_hover_text("Right click to reorder the connected nodes");
+        let mut action = None;
+        response.context_menu(|ui| {
+            let children = ordered_children(snarl, pin.id)
+                .into_iter()
+                .map(|child| snarl[child].title.clone())
+                .collect::<Vec<_>>();
+            action = super::menu::draw_pin_context(ui, &children);
+        });
+
+        if let Some(MenuAction::MoveChild { from, to }) = action
+            && let Some(command) = EditCommand::move_child(snarl, pin.id, from, to)
+        {
+            self.history.execute(snarl, command, self.time);
         }
 
-        return PinInfo::circle().with_fill(pin.color.into());
+        PinInfo::circle().with_fill(color.into())
     }
 
     fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<HyNode>) {
-        let from_pin = &snarl[from.id.node].description.outputs[from.id.output];
-        let to_pin = &snarl[to.id.node].description.inputs[to.id.input];
+        let (Some(from_pin), Some(to_pin)) = (
+            snarl[from.id.node].description.outputs.get(from.id.output),
+            snarl[to.id.node].description.inputs.get(to.id.input),
+        ) else {
+            return;
+        };
 
         if (to.remotes.is_empty() || to_pin.multiple)
             && (from.remotes.is_empty() || from_pin.multiple)
+            && !to.remotes.contains(&from.id)
+            && let Some(wire) = Wire::new(snarl, from.id, to.id)
         {
-            snarl.connect(from.id, to.id);
+            self.history
+                .execute(snarl, EditCommand::Connect(wire), self.time);
         }
     }
 
-    fn has_body(&mut self, node: &HyNode<'b>) -> bool {
+    fn disconnect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<HyNode>) {
+        if let Some(wire) = Wire::new(snarl, from.id, to.id) {
+            self.history
+                .execute(snarl, EditCommand::Disconnect(vec![wire]), self.time);
+        }
+    }
+
+    fn drop_outputs(&mut self, pin: &OutPin, snarl: &mut Snarl<HyNode>) {
+        if let Some(command) = EditCommand::drop_outputs(snarl, pin.id) {
+            self.history.execute(snarl, command, self.time);
+        }
+    }
+
+    fn drop_inputs(&mut self, pin: &InPin, snarl: &mut Snarl<HyNode>) {
+        if let Some(command) = EditCommand::drop_inputs(snarl, pin.id) {
+            self.history.execute(snarl, command, self.time);
+        }
+    }
+
+    fn has_body(&mut self, node: &HyNode) -> bool {
         !node.description.content.is_empty()
     }
 
     fn show_body(
         &mut self,
         node: NodeId,
-        inputs: &[InPin],
-        outputs: &[OutPin],
+        _inputs: &[InPin],
+        _outputs: &[OutPin],
         ui: &mut Ui,
-        snarl: &mut Snarl<HyNode<'b>>,
+        snarl: &mut Snarl<HyNode>,
     ) {
-        if let Some(node) = snarl.get_node_mut(node) {
-            node.draw_content(ui);
+        if let Some(node) = snarl.get_node_mut(node)
+            && let Some((index, before)) = node.draw_content(ui)
+            && let Some((_, after)) = node.values.iter().find(|(i, _)| *i == index)
+            && let Some(content) = node.description.content.get(index)
+        {
+            let command = EditCommand::SetValue {
+                node: node.node_id.clone(),
+                content: content.id.clone(),
+                before,
+                after: after.clone(),
+            };
+            self.history.record(command, self.time);
         }
     }
 }
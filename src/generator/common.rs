@@ -1,12 +1,10 @@
-use std::collections::HashMap;
-
-use egui::pos2;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::editor::{self, value::NodeEditorValueTypes};
+use crate::generator::JsonValue;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
 pub struct Position {
     #[serde(rename = "$x")]
     pub x: i32,
@@ -39,19 +37,13 @@ pub struct WorksheetInfo {
     pub title: String,
     pub workspace_id: String,
     pub groups: Vec<Group>,
+    /// Unknown keys of the editor metadata, kept as is
+    pub metadata: IndexMap<String, JsonValue>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct NodeId(pub String);
 
-
-
-impl Default for Position {
-    fn default() -> Self {
-        Self { x: 0, y: 0 }
-    }
-}
-
 impl NodeId {
     pub fn try_parse(&self) -> Option<(&str, Uuid)> {
         self.0.chars().position(|c| c == '-').and_then(|pos| {
@@ -61,11 +53,10 @@ impl NodeId {
     }
 
     pub fn from_parts(name: &str, uuid: &Uuid) -> Self {
-        Self(format!("{}-{}", name, uuid.as_hyphenated().to_string()))
+        Self(format!("{}-{}", name, uuid.as_hyphenated()))
     }
 
-     pub fn new_rand(name: &str) -> Self {
+    pub fn new_rand(name: &str) -> Self {
         Self::from_parts(name, &Uuid::new_v4())
     }
 }
-
@@ -0,0 +1,172 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    generator::{
+        GeneratorError, JsonValue, common::WorksheetInfo, nodes_v1, nodes_v2, norm::NormalizedNode,
+    },
+    workspace::{schemas::Root, workspace::Workspace},
+};
+
+/// The generation of the asset file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetVersion {
+    /// Editor metadata (`$Position`, `$Title`, ...) is stored inline on every node
+    V1,
+    /// Editor metadata is stored in a single `$NodeEditorMetadata` block on the root
+    V2,
+}
+
+impl AssetVersion {
+    /// Detects the asset format based on the top level keys of the root JSON object
+    pub fn detect(value: &JsonValue) -> Result<Self, GeneratorError> {
+        let root = value.as_object().ok_or_else(|| {
+            GeneratorError::UnexpectedNodeType("/".to_owned(), "object".to_owned())
+        })?;
+
+        if root.contains_key("$NodeEditorMetadata") {
+            Ok(AssetVersion::V2)
+        } else if ["$Position", "$Title", "$WorkspaceID", "$Groups"]
+            .iter()
+            .any(|key| root.contains_key(*key))
+        {
+            Ok(AssetVersion::V1)
+        } else {
+            Err(GeneratorError::UnknownAssetVersion)
+        }
+    }
+
+    /// Reads the `$WorkspaceID` without parsing the whole asset
+    pub fn workspace_id<'a>(&self, value: &'a JsonValue) -> Option<&'a str> {
+        let meta = match self {
+            AssetVersion::V1 => value,
+            AssetVersion::V2 => value.get("$NodeEditorMetadata")?,
+        };
+        meta.get("$WorkspaceID").and_then(JsonValue::as_str)
+    }
+}
+
+/// Finds the root matching the `$WorkspaceID` of an asset (e.g. "HytaleGenerator - Biome").
+/// If the id is not conclusive the directories the asset is located in are used to narrow it down.
+pub fn resolve_root<'a>(
+    workspace: &'a Workspace,
+    workspace_id: &str,
+    path: Option<&Path>,
+) -> Result<&'a Root, GeneratorError> {
+    let roots = &workspace.workspace.roots;
+    let root_key = workspace_id
+        .split_once(" - ")
+        .map(|(_, key)| key)
+        .unwrap_or(workspace_id)
+        .trim();
+
+    let matches = |(key, root): &(&String, &Root), name: &str| {
+        key.eq_ignore_ascii_case(name)
+            || root.menu_name.eq_ignore_ascii_case(name)
+            || root.root_node_type.eq_ignore_ascii_case(name)
+    };
+
+    let mut candidates = roots
+        .iter()
+        .filter(|root| matches(root, root_key))
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        candidates = roots.iter().collect();
+    }
+
+    if candidates.len() > 1
+        && let Some(path) = path
+    {
+        // e.g. "Biomes/Volcanic1/Volcanic1_Shore.json" resolves to the "Biome" root
+        let by_location = path
+            .ancestors()
+            .skip(1)
+            .filter_map(|dir| dir.file_name().and_then(|name| name.to_str()))
+            .find_map(|dir| {
+                let found = candidates
+                    .iter()
+                    .filter(|root| matches(root, dir) || matches(root, dir.trim_end_matches('s')))
+                    .collect::<Vec<_>>();
+                (found.len() == 1).then(|| *found[0])
+            });
+
+        if let Some(root) = by_location {
+            candidates = vec![root];
+        }
+    }
+
+    if let [(_, root)] = candidates.as_slice() {
+        Ok(root)
+    } else {
+        let mut names = candidates
+            .iter()
+            .map(|(_, root)| root.menu_name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        Err(GeneratorError::RootNotResolved(
+            workspace_id.to_owned(),
+            names,
+        ))
+    }
+}
+
+/// An asset file that has been parsed and version checked, but not yet normalized.
+/// This allows picking the workspace based on the `$WorkspaceID` before normalizing
+#[derive(Debug, Clone)]
+pub struct RawAsset {
+    pub version: AssetVersion,
+    pub path: Option<PathBuf>,
+    value: JsonValue,
+}
+
+impl RawAsset {
+    /// Parses an asset JSON of any known version.
+    /// The path is optional and only used to resolve the root node type if the `$WorkspaceID` is ambiguous
+    pub fn parse(content: &str, path: Option<&Path>) -> anyhow::Result<Self> {
+        let value = serde_json::from_str::<JsonValue>(content)?;
+        let version = AssetVersion::detect(&value)?;
+
+        Ok(Self {
+            version,
+            path: path.map(Path::to_path_buf),
+            value,
+        })
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| GeneratorError::ReadError(path.to_path_buf(), err.into()))?;
+        Self::parse(&content, Some(path))
+    }
+
+    pub fn workspace_id(&self) -> Option<&str> {
+        self.version.workspace_id(&self.value)
+    }
+
+    pub fn normalize(
+        self,
+        workspace: &Workspace,
+    ) -> anyhow::Result<(NormalizedNode, WorksheetInfo)> {
+        let root = resolve_root(
+            workspace,
+            self.workspace_id().unwrap_or_default(),
+            self.path.as_deref(),
+        )?;
+        let root_variant = root.root_node_type.as_str();
+
+        let invalid_root = |source| GeneratorError::InvalidNode {
+            path: String::new(),
+            source,
+        };
+        match self.version {
+            AssetVersion::V1 => serde_json::from_value::<nodes_v1::RootNode>(self.value)
+                .map_err(invalid_root)?
+                .normalize(workspace, root_variant),
+            AssetVersion::V2 => serde_json::from_value::<nodes_v2::RootNode>(self.value)
+                .map_err(invalid_root)?
+                .normalize(workspace, root_variant),
+        }
+    }
+}
@@ -1,30 +1,315 @@
 use std::path::PathBuf;
 
+use crate::editor::EditorError;
+
 pub mod common;
-pub mod norm;
+pub mod loader;
 pub mod nodes_v1;
 pub mod nodes_v2;
+pub mod norm;
 
 pub type JsonValue = serde_json::Value;
 pub type JsonNumber = serde_json::Number;
 
+/// Appends a key or index to a JSON pointer (RFC 6901), e.g. `/Layers` + `3` = `/Layers/3`
+pub fn child_pointer(parent: &str, key: &str) -> String {
+    format!("{}/{}", parent, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// The root pointer is empty, which is hard to read in messages
+pub fn display_pointer(pointer: &str) -> &str {
+    if pointer.is_empty() { "/" } else { pointer }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum GeneratorError {
     #[error("Failed to read the file {0}: {1}")]
     ReadError(PathBuf, anyhow::Error),
     #[error("Node variant not resolved {0}")]
     NodeVariantResolve(String),
-    #[error("Key {0} has the wrong type expected {1}")]
+    #[error("{path}: wrong type, expected {expected}", path = display_pointer(.0), expected = .1)]
     UnexpectedNodeType(String, String),
+    #[error("{}: invalid node: {source}", display_pointer(.path))]
+    InvalidNode {
+        path: String,
+        source: serde_json::Error,
+    },
+    #[error("{}: node type of '{key}' in {descriptor} could not be resolved", display_pointer(.path))]
+    VariantNotResolved {
+        path: String,
+        descriptor: String,
+        key: String,
+    },
+    #[error("{}: unknown node type {descriptor}", display_pointer(.path))]
+    UnknownDescriptor { path: String, descriptor: String },
+    #[error("{}: {descriptor} has no output connector for '{key}'", display_pointer(.path))]
+    ConnectorNotResolved {
+        path: String,
+        descriptor: String,
+        key: String,
+    },
+    #[error("{}: {descriptor} has no input for the {output} output connector", display_pointer(.path))]
+    InputNotResolved {
+        path: String,
+        descriptor: String,
+        output: String,
+    },
+    #[error(transparent)]
+    Editor(#[from] EditorError),
+    #[error("Editor node {0} does not exist")]
+    EditorNodeMissing(usize),
+    #[error("Node {0} is connected more than once, asset files have to form a tree")]
+    NodeReused(String),
+    #[error("Unknown asset format, expected either inline editor metadata or $NodeEditorMetadata")]
+    UnknownAssetVersion,
+    #[error("Root node type for workspace id '{0}' could not be resolved, candidates: {candidates}", candidates = .1.join(", "))]
+    RootNotResolved(String, Vec<String>),
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        generator::{nodes_v1, nodes_v2},
-        workspace::{load_descriptions, load_workspace, workspace::Workspace},
+        editor::node::HyNode,
+        generator::{
+            GeneratorError,
+            loader::{self, AssetVersion},
+            nodes_v1, nodes_v2,
+            norm::NormalizedNode,
+        },
+        workspace::{
+            load_descriptions, load_workspace, tests::synthetic_workspace, workspace::Workspace,
+        },
     };
-    use std::{env, fs};
+    use egui::{Vec2, vec2};
+    use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+    use serde_json::{Value as JsonValue, json};
+    use std::{env, fs, path::Path};
+
+    #[test]
+    pub fn test_detect_version() {
+        let v1 = json!({ "$Title": "Test", "$WorkspaceID": "Test - Biome", "$Position": { "$x": 0, "$y": 0 } });
+        let v2 = json!({ "$NodeEditorMetadata": { "$WorkspaceID": "Test - Biome" } });
+
+        assert_eq!(AssetVersion::detect(&v1).unwrap(), AssetVersion::V1);
+        assert_eq!(AssetVersion::detect(&v2).unwrap(), AssetVersion::V2);
+        assert!(AssetVersion::detect(&json!({ "Name": "Test" })).is_err());
+        assert!(AssetVersion::detect(&json!([])).is_err());
+    }
+
+    #[test]
+    pub fn test_resolve_root() {
+        let mut workspace = synthetic_workspace();
+        workspace.workspace.roots.insert(
+            "Cave".to_owned(),
+            serde_json::from_value(json!({ "RootNodeType": "Cave", "MenuName": "Cave" })).unwrap(),
+        );
+
+        let root = loader::resolve_root(&workspace, "Test - Biome", None).unwrap();
+        assert_eq!(root.root_node_type, "Biome");
+
+        let path = Path::new("HytaleGenerator/Biomes/Volcanic1/Volcanic1_Shore.json");
+        let root = loader::resolve_root(&workspace, "Test", Some(path)).unwrap();
+        assert_eq!(root.root_node_type, "Biome");
+
+        let err = loader::resolve_root(&workspace, "Test", None).unwrap_err();
+        assert!(err.to_string().contains("Biome, Cave"));
+    }
+
+    #[test]
+    pub fn test_error_path() {
+        let workspace = synthetic_workspace();
+        let asset = json!({
+            "$Title": "Test",
+            "$WorkspaceID": "Test - Biome",
+            "$Groups": [],
+            "Terrain": {
+                "Type": "Sum",
+                "Inputs": [
+                    { "Type": "Constant", "Value": 1.0 },
+                    { "Type": "Unknown" }
+                ]
+            }
+        });
+
+        let asset = loader::RawAsset::parse(&asset.to_string(), None).unwrap();
+        let err = asset.normalize(&workspace).unwrap_err();
+        let err = err.downcast::<GeneratorError>().unwrap();
+        assert!(matches!(
+            &err,
+            GeneratorError::VariantNotResolved { path, descriptor, .. }
+                if path == "/Terrain/Inputs/1" && descriptor == "SumDensity"
+        ));
+    }
+
+    #[test]
+    pub fn test_multi_input_connection() {
+        let base = synthetic_workspace();
+        let mut nodes = base
+            .nodes
+            .iter()
+            .map(|desc| desc.as_ref().clone())
+            .filter(|desc| desc.id != "ConstantDensity")
+            .collect::<Vec<_>>();
+        nodes.push(
+            serde_json::from_value(json!({
+                "Id": "ConstantDensity",
+                "Title": "Constant",
+                "Color": "Blue",
+                "Inputs": [
+                    { "Id": "Curve", "Type": "Curve", "Color": "Red" },
+                    { "Id": "Input", "Type": "Density", "Color": "Blue" }
+                ]
+            }))
+            .unwrap(),
+        );
+        let workspace = Workspace::construct(base.workspace.clone(), nodes);
+
+        let asset = json!({
+            "Terrain": { "Type": "Constant" },
+            "$NodeEditorMetadata": { "$WorkspaceID": "Test - Biome", "$Groups": [], "$Nodes": {} }
+        });
+        let asset = loader::RawAsset::parse(&asset.to_string(), None).unwrap();
+        let (norm, _) = asset.normalize(&workspace).unwrap();
+        let (connections, _, _) = norm.to_editor(&workspace).unwrap();
+
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].from_connector, 0);
+        assert_eq!(connections[0].to_connector, 1);
+    }
+
+    /// Loads a V2 asset into a snarl and saves it again, returns the saved JSON and the offset
+    fn v2_round_trip(original: &JsonValue) -> (JsonValue, Vec2) {
+        let workspace = synthetic_workspace();
+        let asset = loader::RawAsset::parse(&original.to_string(), None).unwrap();
+        assert_eq!(asset.version, AssetVersion::V2);
+        let (norm, info) = asset.normalize(&workspace).unwrap();
+
+        let mut snarl = Snarl::<HyNode>::new();
+        let (connections, nodes, offset) = norm.to_editor(&workspace).unwrap();
+        for node in nodes.into_iter() {
+            snarl.insert_node(node.pos, node.try_into().unwrap());
+        }
+        for connection in connections.iter() {
+            snarl.connect(
+                OutPinId {
+                    node: NodeId(connection.from_node),
+                    output: connection.from_connector,
+                },
+                InPinId {
+                    node: NodeId(connection.to_node),
+                    input: connection.to_connector,
+                },
+            );
+        }
+
+        let norm = NormalizedNode::from_editor(&snarl, NodeId(0), &workspace, offset).unwrap();
+        let saved = nodes_v2::RootNode::from_normalized(norm, &info, &workspace).unwrap();
+        let saved = serde_json::to_value(saved).unwrap();
+        (saved, offset)
+    }
+
+    #[test]
+    pub fn test_v2_save_round_trip() {
+        let original = json!({
+            "$NodeId": "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11",
+            "Terrain": {
+                "$NodeId": "SumDensity-5b1e3f0a-0c55-4a2e-8b8e-3f6cb9e1e7a2",
+                "Inputs": [{
+                    "$NodeId": "ConstantDensity-0b7c2f7e-2f4b-4d85-9a8c-7b9a3d7e5c10",
+                    "Type": "Constant",
+                    "Value": 1.5
+                }, {
+                    "$NodeId": "ConstantDensity-6a0f3c9d-1b2e-4f7a-8c5d-2e9b7a4f1c03",
+                    "Value": -2.0,
+                    // Alias of Constant, which is the key used for new nodes
+                    "Type": "Fixed",
+                    "$Custom": { "Keep": true }
+                }, {
+                    // Missing content keys must not be written with their default
+                    "$NodeId": "ConstantDensity-3c5e7a91-4d2b-4e6f-9a1c-8b7d5f3e2a14",
+                    "Type": "Constant"
+                }],
+                "Type": "Sum"
+            },
+            "Name": "Test",
+            "$NodeEditorMetadata": {
+                "$Title": "Test",
+                "$WorkspaceID": "Test - Biome",
+                "$Groups": [],
+                "$Nodes": {
+                    "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11": {
+                        "$Position": { "$x": 10, "$y": 20 },
+                        "$Collapsed": true
+                    },
+                    "SumDensity-5b1e3f0a-0c55-4a2e-8b8e-3f6cb9e1e7a2": { "$Position": { "$x": 300, "$y": 20 } },
+                    "ConstantDensity-0b7c2f7e-2f4b-4d85-9a8c-7b9a3d7e5c10": { "$Position": { "$x": 600, "$y": -40 } },
+                    "ConstantDensity-6a0f3c9d-1b2e-4f7a-8c5d-2e9b7a4f1c03": { "$Position": { "$x": 600, "$y": 80 } },
+                    "ConstantDensity-3c5e7a91-4d2b-4e6f-9a1c-8b7d5f3e2a14": { "$Position": { "$x": 600, "$y": 200 } }
+                },
+                "$FloatingNodes": []
+            }
+        });
+
+        let (saved, offset) = v2_round_trip(&original);
+
+        // to_editor moves the nodes to 0,0, saving has to restore the positions of the file
+        assert_eq!(offset, vec2(10.0, -40.0));
+        // Compared as text, as map equality ignores the key order
+        assert_eq!(saved.to_string(), original.to_string());
+    }
+
+    #[test]
+    pub fn test_v2_save_single_object_output() {
+        // Inputs allows multiple children, a single one written as an object stays an object
+        let original = json!({
+            "$NodeId": "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11",
+            "Terrain": {
+                "$NodeId": "SumDensity-5b1e3f0a-0c55-4a2e-8b8e-3f6cb9e1e7a2",
+                "Type": "Sum",
+                "Inputs": {
+                    "$NodeId": "ConstantDensity-0b7c2f7e-2f4b-4d85-9a8c-7b9a3d7e5c10",
+                    "Type": "Constant",
+                    "Value": 1.5
+                }
+            },
+            "$NodeEditorMetadata": {
+                "$Title": "Test",
+                "$WorkspaceID": "Test - Biome",
+                "$Groups": [],
+                "$Nodes": {
+                    "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11": { "$Position": { "$x": 0, "$y": 0 } },
+                    "SumDensity-5b1e3f0a-0c55-4a2e-8b8e-3f6cb9e1e7a2": { "$Position": { "$x": 300, "$y": 0 } },
+                    "ConstantDensity-0b7c2f7e-2f4b-4d85-9a8c-7b9a3d7e5c10": { "$Position": { "$x": 600, "$y": 0 } }
+                },
+                "$FloatingNodes": []
+            }
+        });
+
+        let (saved, _) = v2_round_trip(&original);
+        assert_eq!(saved.to_string(), original.to_string());
+    }
+
+    #[test]
+    pub fn test_v2_save_mismatching_values() {
+        // Values not matching their content type are written back unchanged
+        let original = json!({
+            "$NodeId": "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11",
+            "Seed": 2.5,
+            "Enabled": "yes",
+            "$NodeEditorMetadata": {
+                "$Title": "Test",
+                "$WorkspaceID": "Test - Biome",
+                "$Groups": [],
+                "$Nodes": {
+                    "Biome-8d4a7b4e-6f61-4c36-9a38-1d0c8e0c2b11": { "$Position": { "$x": 0, "$y": 0 } }
+                },
+                "$FloatingNodes": []
+            }
+        });
+
+        let (saved, _) = v2_round_trip(&original);
+        assert_eq!(saved.to_string(), original.to_string());
+    }
 
     #[test]
     pub fn test_basic_biome() {
@@ -1,13 +1,12 @@
 use core::f32;
-use std::collections::HashMap;
-
-use egui::{pos2, vec2};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    editor::{self, node::HyConnection, value::NodeEditorValueTypes},
     generator::{
-        GeneratorError, JsonValue, common::{Group, NodeId, Position, WorksheetInfo}, norm::NormalizedNode
+        GeneratorError, JsonValue, child_pointer,
+        common::{Group, NodeId, Position, WorksheetInfo},
+        norm::NormalizedNode,
     },
     workspace::{nodes::NodeDescription, workspace::Workspace},
 };
@@ -25,7 +24,7 @@ pub struct Node {
     pub node_id: Option<NodeId>,
 
     #[serde(flatten)]
-    pub values: HashMap<String, JsonValue>,
+    pub values: IndexMap<String, JsonValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,7 +39,6 @@ pub struct RootNode {
     pub node: Node,
 }
 
-
 impl RootNode {
     pub fn normalize(
         self,
@@ -48,90 +46,99 @@ impl RootNode {
         root_variant: &str,
     ) -> anyhow::Result<(NormalizedNode, WorksheetInfo)> {
         let root_desc = workspace
-            .nodes
-            .iter()
-            .find(|node| node.id == root_variant)
-            .expect("TODO");
-        let normal = self.node.normalize(workspace, &root_desc)?;
+            .get_description(root_variant)
+            .ok_or_else(|| GeneratorError::NodeVariantResolve(root_variant.to_owned()))?;
+        let normal = self.node.normalize(workspace, root_desc, "")?;
         Ok((
             normal,
             WorksheetInfo {
                 title: self.title,
                 workspace_id: self.workspace_id,
                 groups: self.groups,
+                metadata: IndexMap::new(),
             },
         ))
     }
 }
 
 impl Node {
+    /// Normalizes the node located at the JSON pointer `path` in the asset
     pub fn normalize(
         self,
         workspace: &Workspace,
         description: &NodeDescription,
+        path: &str,
     ) -> anyhow::Result<NormalizedNode> {
-        let mut remaining = HashMap::new();
-        let mut outputs = HashMap::new();
+        let mut remaining = IndexMap::new();
+        let mut outputs = IndexMap::new();
+        let key_order = self.values.keys().cloned().collect();
+        let mut object_outputs = Vec::new();
+
+        for (key, value) in self.values.into_iter() {
+            if description.get_pin(&key).is_none() {
+                remaining.insert(key, value);
+                continue;
+            }
 
-        for value in self.values.into_iter() {
-            if let Some(_pin) = description.get_pin(&value.0) {
-                match value.1 {
-                    obj @ JsonValue::Object(_) => {
-                        let node = serde_json::from_value::<Node>(obj)?;
-                        let node_values: &_ = &node.values;
-                        let sub_description = description
-                            .get_variant(workspace, &value.0, |var_key| {
-                                node_values.get(var_key).and_then(|val| match val {
-                                    JsonValue::String(value) => Some(value.as_str()),
-                                    _ => None,
-                                })
-                            })
-                            .ok_or_else(|| {
-                                super::GeneratorError::NodeVariantResolve(value.0.clone())
-                            })?;
+            let key_path = child_pointer(path, &key);
+            let children = match value {
+                obj @ JsonValue::Object(_) => {
+                    object_outputs.push(key.clone());
+                    vec![(key_path, obj)]
+                }
+                JsonValue::Array(values) => values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, obj)| (child_pointer(&key_path, &index.to_string()), obj))
+                    .collect(),
+                _ => {
+                    return Err(GeneratorError::UnexpectedNodeType(
+                        key_path,
+                        "object or array".to_owned(),
+                    )
+                    .into());
+                }
+            };
 
-                        outputs.insert(value.0, vec![node.normalize(workspace, sub_description)?]);
+            let mut list = Vec::with_capacity(children.len());
+            for (child_path, obj) in children.into_iter() {
+                let node = serde_json::from_value::<Node>(obj).map_err(|source| {
+                    GeneratorError::InvalidNode {
+                        path: child_path.clone(),
+                        source,
                     }
-                    JsonValue::Array(values) => {
-                        let mut list = Vec::with_capacity(values.len());
-                        for obj in values.into_iter() {
-                            let node = serde_json::from_value::<Node>(obj)?;
-                            let node_values: &_ = &node.values;
-                            let sub_description = description
-                                .get_variant(workspace, &value.0, |var_key| {
-                                    node_values.get(var_key).and_then(|val| match val {
-                                        JsonValue::String(value) => Some(value.as_str()),
-                                        _ => None,
-                                    })
-                                })
-                                .ok_or_else(|| {
-                                    super::GeneratorError::NodeVariantResolve(value.0.clone())
-                                })?;
+                })?;
+                let node_values: &_ = &node.values;
+                let sub_description = description
+                    .get_variant(workspace, &key, |var_key| {
+                        node_values.get(var_key).and_then(|val| match val {
+                            JsonValue::String(value) => Some(value.as_str()),
+                            _ => None,
+                        })
+                    })
+                    .ok_or_else(|| GeneratorError::VariantNotResolved {
+                        path: child_path.clone(),
+                        descriptor: description.id.clone(),
+                        key: key.clone(),
+                    })?;
 
-                            list.push(node.normalize(workspace, sub_description)?);
-                        }
-                        outputs.insert(value.0, list);
-                    }
-                    _ => {
-                        return Err(super::GeneratorError::UnexpectedNodeType(
-                            value.0,
-                            "object".to_owned(),
-                        )
-                        .into());
-                    }
-                };
-            } else {
-                remaining.insert(value.0, value.1);
+                list.push(node.normalize(workspace, sub_description, &child_path)?);
             }
+            outputs.insert(key, list);
         }
 
         Ok(NormalizedNode {
             position: self.position,
             comment: self.comment,
             node_id: self.node_id,
+            path: path.to_owned(),
             variant: description.id.clone(),
+            description: None,
             values: remaining,
-            outputs: outputs,
+            outputs,
+            key_order,
+            object_outputs,
+            metadata: IndexMap::new(),
         })
     }
 }
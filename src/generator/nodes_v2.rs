@@ -1,13 +1,10 @@
 use core::f32;
-use std::collections::HashMap;
-
-use egui::{pos2, vec2};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    editor::{self, node::HyConnection, value::NodeEditorValueTypes},
     generator::{
-        GeneratorError,
+        GeneratorError, child_pointer,
         common::{Group, NodeId, Position, WorksheetInfo},
         norm::NormalizedNode,
     },
@@ -17,8 +14,8 @@ use crate::{
 pub type JsonValue = serde_json::Value;
 pub type JsonNumber = serde_json::Number;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde_with::skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Node {
     #[serde(rename = "$Comment")]
     pub comment: Option<String>,
@@ -27,7 +24,7 @@ pub struct Node {
     pub node_id: Option<NodeId>,
 
     #[serde(flatten)]
-    pub values: HashMap<String, JsonValue>,
+    pub values: IndexMap<String, JsonValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,13 +45,18 @@ pub struct WorkspaceMeta {
     #[serde(rename = "$Groups")]
     pub groups: Vec<Group>,
     #[serde(rename = "$Nodes")]
-    pub nodes: HashMap<String, NodeMeta>,
+    pub nodes: IndexMap<String, NodeMeta>,
+    /// Metadata the editor doesn't know (yet), kept as is
+    #[serde(flatten)]
+    pub extra: IndexMap<String, JsonValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NodeMeta {
     #[serde(rename = "$Position", default)]
     pub position: Position,
+    #[serde(flatten)]
+    pub extra: IndexMap<String, JsonValue>,
 }
 
 impl RootNode {
@@ -64,101 +66,190 @@ impl RootNode {
         root_variant: &str,
     ) -> anyhow::Result<(NormalizedNode, WorksheetInfo)> {
         let root_desc = workspace
-            .nodes
-            .iter()
-            .find(|node| node.id == root_variant)
-            .expect("TODO");
+            .get_description(root_variant)
+            .ok_or_else(|| GeneratorError::NodeVariantResolve(root_variant.to_owned()))?;
         let normal = self
             .node
-            .normalize(workspace, &root_desc, &self.workspace)?;
+            .normalize(workspace, root_desc, &self.workspace, "")?;
         Ok((
             normal,
             WorksheetInfo {
                 title: self.workspace.title,
                 workspace_id: self.workspace.workspace_id,
                 groups: self.workspace.groups,
+                metadata: self.workspace.extra,
             },
         ))
     }
 }
 
+impl RootNode {
+    /// Builds the v2 asset representation of a normalized node tree.
+    /// This is the inverse of [`RootNode::normalize`]
+    pub fn from_normalized(
+        node: NormalizedNode,
+        info: &WorksheetInfo,
+        workspace: &Workspace,
+    ) -> anyhow::Result<Self> {
+        let mut nodes = IndexMap::new();
+        let node = Node::from_normalized(node, workspace, &mut nodes)?;
+
+        Ok(RootNode {
+            node,
+            workspace: WorkspaceMeta {
+                title: info.title.clone(),
+                workspace_id: info.workspace_id.clone(),
+                groups: info.groups.clone(),
+                nodes,
+                extra: info.metadata.clone(),
+            },
+        })
+    }
+}
+
 impl Node {
+    pub fn from_normalized(
+        node: NormalizedNode,
+        workspace: &Workspace,
+        ws_nodes: &mut IndexMap<String, NodeMeta>,
+    ) -> anyhow::Result<Self> {
+        let description = match &node.description {
+            Some(description) => description.clone(),
+            None => workspace
+                .get_description(&node.variant)
+                .cloned()
+                .ok_or_else(|| GeneratorError::NodeVariantResolve(node.variant.clone()))?,
+        };
+
+        let node_id = node
+            .node_id
+            .unwrap_or_else(|| NodeId::new_rand(&description.id));
+        ws_nodes.insert(
+            node_id.0.clone(),
+            NodeMeta {
+                position: node.position,
+                extra: node.metadata,
+            },
+        );
+
+        let mut values = node.values;
+        for (key, children) in node.outputs.into_iter() {
+            let multiple = description
+                .get_connector(&key)
+                .map(|(_, connector)| connector.multiple)
+                .unwrap_or(true);
+
+            let mut list = Vec::with_capacity(children.len());
+            for child in children.into_iter() {
+                list.push(serde_json::to_value(Node::from_normalized(
+                    child, workspace, ws_nodes,
+                )?)?);
+            }
+
+            // A single child keeps the shape it had in the loaded file
+            if list.len() == 1 && (!multiple || node.object_outputs.contains(&key)) {
+                values.insert(key, list.remove(0));
+            } else {
+                values.insert(key, JsonValue::Array(list));
+            }
+        }
+        // Keys keep the position they had in the loaded file, new ones are appended
+        values.sort_by_cached_key(|key, _| {
+            node.key_order
+                .iter()
+                .position(|ordered| ordered == key)
+                .unwrap_or(usize::MAX)
+        });
+
+        Ok(Node {
+            comment: node.comment,
+            node_id: Some(node_id),
+            values,
+        })
+    }
+
+    /// Normalizes the node located at the JSON pointer `path` in the asset
     pub fn normalize(
         self,
         workspace: &Workspace,
         description: &NodeDescription,
         ws_meta: &WorkspaceMeta,
+        path: &str,
     ) -> anyhow::Result<NormalizedNode> {
-        let mut remaining = HashMap::new();
-        let mut outputs = HashMap::new();
-
-        for value in self.values.into_iter() {
-            if let Some(_pin) = description.get_pin(&value.0) {
-                match value.1 {
-                    obj @ JsonValue::Object(_) => {
-                        let node = serde_json::from_value::<Node>(obj)?;
-                        let node_values: &_ = &node.values;
-                        let sub_description = description
-                            .get_variant(workspace, &value.0, |var_key| {
-                                node_values.get(var_key).and_then(|val| match val {
-                                    JsonValue::String(value) => Some(value.as_str()),
-                                    _ => None,
-                                })
-                            })
-                            .ok_or_else(|| {
-                                super::GeneratorError::NodeVariantResolve(value.0.clone())
-                            })?;
-
-                        outputs.insert(
-                            value.0,
-                            vec![node.normalize(workspace, sub_description, ws_meta)?],
-                        );
-                    }
-                    JsonValue::Array(values) => {
-                        let mut list = Vec::with_capacity(values.len());
-                        for obj in values.into_iter() {
-                            let node = serde_json::from_value::<Node>(obj)?;
-                            let node_values: &_ = &node.values;
-                            let sub_description = description
-                                .get_variant(workspace, &value.0, |var_key| {
-                                    node_values.get(var_key).and_then(|val| match val {
-                                        JsonValue::String(value) => Some(value.as_str()),
-                                        _ => None,
-                                    })
-                                })
-                                .ok_or_else(|| {
-                                    super::GeneratorError::NodeVariantResolve(value.0.clone())
-                                })?;
-
-                            list.push(node.normalize(workspace, sub_description, ws_meta)?);
-                        }
-                        outputs.insert(value.0, list);
-                    }
-                    _ => {
-                        return Err(super::GeneratorError::UnexpectedNodeType(
-                            value.0,
-                            "object".to_owned(),
-                        )
-                        .into());
+        let mut remaining = IndexMap::new();
+        let mut outputs = IndexMap::new();
+        let key_order = self.values.keys().cloned().collect();
+        let mut object_outputs = Vec::new();
+
+        for (key, value) in self.values.into_iter() {
+            if description.get_pin(&key).is_none() {
+                remaining.insert(key, value);
+                continue;
+            }
+
+            let key_path = child_pointer(path, &key);
+            let children = match value {
+                obj @ JsonValue::Object(_) => {
+                    object_outputs.push(key.clone());
+                    vec![(key_path, obj)]
+                }
+                JsonValue::Array(values) => values
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, obj)| (child_pointer(&key_path, &index.to_string()), obj))
+                    .collect(),
+                _ => {
+                    return Err(GeneratorError::UnexpectedNodeType(
+                        key_path,
+                        "object or array".to_owned(),
+                    )
+                    .into());
+                }
+            };
+
+            let mut list = Vec::with_capacity(children.len());
+            for (child_path, obj) in children.into_iter() {
+                let node = serde_json::from_value::<Node>(obj).map_err(|source| {
+                    GeneratorError::InvalidNode {
+                        path: child_path.clone(),
+                        source,
                     }
-                };
-            } else {
-                remaining.insert(value.0, value.1);
+                })?;
+                let node_values: &_ = &node.values;
+                let sub_description = description
+                    .get_variant(workspace, &key, |var_key| {
+                        node_values.get(var_key).and_then(|val| match val {
+                            JsonValue::String(value) => Some(value.as_str()),
+                            _ => None,
+                        })
+                    })
+                    .ok_or_else(|| GeneratorError::VariantNotResolved {
+                        path: child_path.clone(),
+                        descriptor: description.id.clone(),
+                        key: key.clone(),
+                    })?;
+
+                list.push(node.normalize(workspace, sub_description, ws_meta, &child_path)?);
             }
+            outputs.insert(key, list);
         }
 
+        let meta = self
+            .node_id
+            .as_ref()
+            .and_then(|idx| ws_meta.nodes.get(&idx.0));
         Ok(NormalizedNode {
-            position: self
-                .node_id
-                .as_ref()
-                .and_then(|idx| ws_meta.nodes.get(&idx.0))
-                .map(|meta| meta.position)
-                .unwrap_or_default(),
+            position: meta.map(|meta| meta.position).unwrap_or_default(),
             comment: self.comment,
             node_id: self.node_id,
+            path: path.to_owned(),
             variant: description.id.clone(),
+            description: None,
             values: remaining,
-            outputs: outputs,
+            outputs,
+            key_order,
+            object_outputs,
+            metadata: meta.map(|meta| meta.extra.clone()).unwrap_or_default(),
         })
     }
 }
@@ -1,14 +1,23 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use egui::{pos2, vec2};
+use egui::{Vec2, pos2, vec2};
+use egui_snarl::{OutPinId, Snarl};
+use indexmap::IndexMap;
 
 use crate::{
-    editor::{self, node::HyConnection, value::NodeEditorValueTypes},
+    editor::{
+        self, EditorError,
+        node::{HyConnection, HyNode, ordered_children},
+        value::NodeEditorValueTypes,
+    },
     generator::{
-        JsonValue,
+        GeneratorError, JsonValue, child_pointer,
         common::{NodeId, Position},
     },
-    workspace::workspace::Workspace,
+    workspace::{nodes::NodeDescription, workspace::Workspace},
 };
 
 #[derive(Debug, Clone)]
@@ -16,27 +25,34 @@ pub struct NormalizedNode {
     pub position: Position,
     pub comment: Option<String>,
     pub node_id: Option<NodeId>,
+    /// JSON pointer of the node in the asset file, used in error messages
+    pub path: String,
     pub variant: String,
-    pub values: HashMap<String, JsonValue>,
-    pub outputs: HashMap<String, Vec<NormalizedNode>>,
+    /// Description of the editor node, set by [`NormalizedNode::from_editor`]. Nodes whose
+    /// descriptor is missing from a reloaded workspace keep their previous one, the workspace is
+    /// only used if this is unset
+    pub description: Option<Arc<NodeDescription>>,
+    pub values: IndexMap<String, JsonValue>,
+    pub outputs: IndexMap<String, Vec<NormalizedNode>>,
+    /// Order of all keys (values and outputs) in the asset file
+    pub key_order: Vec<String>,
+    /// Outputs written as a single object instead of an array in the asset file
+    pub object_outputs: Vec<String>,
+    /// Editor metadata of the node the editor doesn't know (yet), kept as is
+    pub metadata: IndexMap<String, JsonValue>,
 }
 
 impl NormalizedNode {
-    pub fn to_editor<'a>(
+    /// Positions are moved so the top left node is at 0,0, the returned offset has to be passed to
+    /// [`NormalizedNode::from_editor`] to restore them when saving
+    pub fn to_editor(
         &self,
-        workspace: &'a Workspace,
-    ) -> (Vec<HyConnection>, Vec<editor::node::HyNodeProto<'a>>) {
+        workspace: &Workspace,
+    ) -> Result<(Vec<HyConnection>, Vec<editor::node::HyNodeProto>, Vec2), GeneratorError> {
         let mut connections = Vec::new();
         let mut nodes = Vec::new();
 
-        let node_map = workspace
-            .nodes
-            .iter()
-            .enumerate()
-            .map(|node| (node.1.id.clone(), node.0))
-            .collect::<HashMap<_, _>>();
-
-        self.to_editor_internal(&node_map, workspace, &mut connections, &mut nodes);
+        self.to_editor_internal(workspace, &mut connections, &mut nodes)?;
 
         let mut x_offset = f32::INFINITY;
         let mut y_offset = f32::INFINITY;
@@ -45,69 +61,212 @@ impl NormalizedNode {
             y_offset = y_offset.min(pos.y);
         }
 
-        nodes
-            .iter_mut()
-            .for_each(|node| node.pos = node.pos - vec2(x_offset, y_offset));
+        let offset = vec2(x_offset, y_offset);
+        nodes.iter_mut().for_each(|node| node.pos -= offset);
 
-        (connections, nodes)
+        Ok((connections, nodes, offset))
     }
 
-    fn to_editor_internal<'a>(
+    /// Rebuilds the normalized node tree from the editor graph starting at the given root.
+    /// This is the inverse of [`NormalizedNode::to_editor`], `offset` is added to all positions
+    pub fn from_editor(
+        snarl: &Snarl<HyNode>,
+        root: egui_snarl::NodeId,
+        workspace: &Workspace,
+        offset: Vec2,
+    ) -> Result<NormalizedNode, GeneratorError> {
+        let mut visited = HashSet::new();
+        Self::from_editor_internal(snarl, root, workspace, offset, &mut visited, String::new())
+    }
+
+    fn from_editor_internal(
+        snarl: &Snarl<HyNode>,
+        id: egui_snarl::NodeId,
+        workspace: &Workspace,
+        offset: Vec2,
+        visited: &mut HashSet<egui_snarl::NodeId>,
+        path: String,
+    ) -> Result<NormalizedNode, GeneratorError> {
+        let info = snarl
+            .get_node_info(id)
+            .ok_or(GeneratorError::EditorNodeMissing(id.0))?;
+        let node = &info.value;
+        let desc = &node.description;
+
+        // The asset format is a tree, a node can't be a child of multiple pins (or of itself)
+        if !visited.insert(id) {
+            return Err(GeneratorError::NodeReused(node.node_id.0.clone()));
+        }
+
+        let mut values = node.extra_values.clone();
+        for (content, value) in node.contents() {
+            let present = node.key_order.contains(&content.id);
+            if let Some(json) = value.to_json_entry(&content.options, present) {
+                values.insert(content.id.clone(), json);
+            }
+        }
+
+        let mut outputs = IndexMap::new();
+        for (index, connector) in desc.outputs.iter().enumerate() {
+            let Some((key, pin)) = desc.get_schema_pin(&connector.id) else {
+                continue;
+            };
+
+            let remotes = ordered_children(
+                snarl,
+                OutPinId {
+                    node: id,
+                    output: index,
+                },
+            );
+
+            let key_path = child_pointer(&path, key);
+            let mut children = Vec::with_capacity(remotes.len());
+            for (child_index, remote) in remotes.into_iter().enumerate() {
+                let child_path = if connector.multiple {
+                    child_pointer(&key_path, &child_index.to_string())
+                } else {
+                    key_path.clone()
+                };
+                let mut child = Self::from_editor_internal(
+                    snarl, remote, workspace, offset, visited, child_path,
+                )?;
+                // A key from the file that is an alias for the same descriptor is kept as it is
+                if let Some((field, variant_key)) =
+                    workspace.get_variant_key(&pin.node, &child.variant)
+                {
+                    let current = child
+                        .values
+                        .get(field)
+                        .and_then(JsonValue::as_str)
+                        .and_then(|key| workspace.resolve_variant(&pin.node, key));
+                    if current.is_none_or(|desc| desc.id != child.variant) {
+                        child
+                            .values
+                            .insert(field.to_owned(), JsonValue::from(variant_key));
+                    }
+                }
+                children.push(child);
+            }
+
+            if !children.is_empty() {
+                outputs.insert(key.to_owned(), children);
+            }
+        }
+
+        Ok(NormalizedNode {
+            position: Position {
+                x: (info.pos.x + offset.x).round() as i32,
+                y: (info.pos.y + offset.y).round() as i32,
+            },
+            comment: node.comment.clone(),
+            node_id: Some(node.node_id.clone()),
+            path,
+            variant: desc.id.clone(),
+            description: Some(desc.clone()),
+            values,
+            outputs,
+            key_order: node.key_order.clone(),
+            object_outputs: node.object_outputs.clone(),
+            metadata: node.metadata.clone(),
+        })
+    }
+
+    fn to_editor_internal(
         &self,
-        node_map: &HashMap<String, usize>,
-        workspace: &'a Workspace,
+        workspace: &Workspace,
         connections: &mut Vec<HyConnection>,
-        nodes: &mut Vec<editor::node::HyNodeProto<'a>>,
-    ) -> usize {
-        // FIXME: Replace unwrap with propper error handling!
-        let desc_index = node_map.get(&self.variant).unwrap();
-        let desc = &workspace.nodes[*desc_index];
+        nodes: &mut Vec<editor::node::HyNodeProto>,
+    ) -> Result<usize, GeneratorError> {
+        let desc = workspace.get_description(&self.variant).ok_or_else(|| {
+            GeneratorError::UnknownDescriptor {
+                path: self.path.clone(),
+                descriptor: self.variant.clone(),
+            }
+        })?;
         let new_id = nodes.len();
 
-        let values = desc
-            .content
+        let mut values = HashMap::with_capacity(desc.content.len());
+        for content in desc.content.iter() {
+            let value = self
+                .values
+                .get(&content.id)
+                .cloned()
+                .unwrap_or(content.options.get_default().0);
+            let value =
+                NodeEditorValueTypes::from_value(value, &content.options).map_err(|source| {
+                    EditorError::InvalidValue {
+                        path: self.path.clone(),
+                        descriptor: desc.id.clone(),
+                        content: content.id.clone(),
+                        source: Box::new(source),
+                    }
+                })?;
+            values.insert(content.id.clone(), value);
+        }
+
+        let extra_values = self
+            .values
             .iter()
-            .map(|content| {
-                (
-                    content.id.clone(),
-                    NodeEditorValueTypes::from_value(
-                        self.values
-                            .get(&content.id)
-                            .map(Clone::clone)
-                            .unwrap_or(content.options.get_default().0),
-                        &content.options,
-                    )
-                    .unwrap(),
-                )
-            })
+            .filter(|(key, _)| !desc.content.iter().any(|content| &content.id == *key))
+            .map(|(key, value)| (key.clone(), value.clone()))
             .collect();
 
         nodes.push(editor::node::HyNodeProto {
             pos: pos2(self.position.x as f32, self.position.y as f32),
-            variant_index: *desc_index,
-            workspace,
-            values: values,
+            description: desc.clone(),
+            node_id: self
+                .node_id
+                .clone()
+                .unwrap_or_else(|| NodeId::new_rand(&desc.id)),
+            path: self.path.clone(),
+            comment: self.comment.clone(),
+            values,
+            extra_values,
+            child_order: HashMap::new(),
+            key_order: self.key_order.clone(),
+            object_outputs: self.object_outputs.clone(),
+            metadata: self.metadata.clone(),
         });
 
-        self.outputs
-            .iter()
-            .enumerate()
-            .for_each(|(index, (connector_name, new_nodes))| {
-                // FIXME: Replace unwrap with propper error handling!
-                let conn_index = desc.get_connector(connector_name).unwrap().0;
-
-                new_nodes.iter().for_each(|node| {
-                    let sub_id = node.to_editor_internal(node_map, workspace, connections, nodes);
-
-                    connections.push(HyConnection {
-                        from_node: new_id,
-                        from_connector: conn_index,
-                        to_node: sub_id,
-                        to_connector: 0, //TODO: Figure out how multi-input nodes are handled
-                    });
+        for (connector_name, new_nodes) in self.outputs.iter() {
+            let not_resolved = || GeneratorError::ConnectorNotResolved {
+                path: self.path.clone(),
+                descriptor: desc.id.clone(),
+                key: connector_name.clone(),
+            };
+            let pin = desc.get_pin(connector_name).ok_or_else(not_resolved)?;
+            let (conn_index, connector) = desc
+                .get_connector(connector_name)
+                .ok_or_else(not_resolved)?;
+
+            for node in new_nodes.iter() {
+                let sub_id = node.to_editor_internal(workspace, connections, nodes)?;
+                let child = &nodes[sub_id].description;
+                let to_connector = child.get_input_for(pin, connector).ok_or_else(|| {
+                    GeneratorError::InputNotResolved {
+                        path: node.path.clone(),
+                        descriptor: child.id.clone(),
+                        output: connector.id.clone(),
+                    }
+                })?;
+
+                connections.push(HyConnection {
+                    from_node: new_id,
+                    from_connector: conn_index,
+                    to_node: sub_id,
+                    to_connector,
                 });
-            });
 
-        new_id
+                let child_id = nodes[sub_id].node_id.clone();
+                nodes[new_id]
+                    .child_order
+                    .entry(connector.id.clone())
+                    .or_default()
+                    .push(child_id);
+            }
+        }
+
+        Ok(new_id)
     }
 }
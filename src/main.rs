@@ -1,14 +1,39 @@
-use crate::app::HyNodeEditor;
+use std::{env, process};
+
+use crate::{
+    app::HyNodeEditor,
+    settings::{Settings, SettingsError},
+};
 
 mod app;
+mod dialogs;
+mod document;
 mod editor;
 mod errors;
 mod generator;
+mod settings;
 mod workspace;
 
 fn main() -> eframe::Result {
     env_logger::init();
 
+    let stored_settings = Settings::load();
+    let mut settings = stored_settings.clone();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|arg| arg == "validate") {
+        let folders = settings
+            .apply_options(args.into_iter().skip(1))
+            .unwrap_or_else(|err| usage_error(err));
+        process::exit(workspace::validate::run_command(
+            &settings.workspaces_root,
+            &folders,
+        ));
+    }
+
+    if let Err(err) = settings.apply_args(args) {
+        usage_error(err);
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1500.0, 900.0]),
 
@@ -19,7 +44,12 @@ fn main() -> eframe::Result {
         options,
         Box::new(|cc| {
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(HyNodeEditor::build(cc))
+            Ok(HyNodeEditor::build(cc, stored_settings, settings))
         }),
     )
 }
+
+fn usage_error(err: SettingsError) -> ! {
+    eprintln!("{}\n{}", err, Settings::USAGE);
+    process::exit(2);
+}
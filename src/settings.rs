@@ -0,0 +1,210 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SettingsError {
+    #[error("Missing value for argument {0}")]
+    MissingValue(String),
+    #[error("Unknown argument {0}")]
+    UnknownArgument(String),
+}
+
+/// Paths the editor works with, persisted between sessions and overridable from the command line
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Settings {
+    /// Directory containing all workspace folders (the renamed `Workspaces` folder of the NodeEditor)
+    pub workspaces_root: PathBuf,
+    /// Name of the workspace folder inside of `workspaces_root`
+    pub workspace: String,
+    /// Root of the extracted asset files
+    pub asset_root: PathBuf,
+    /// Asset file opened on startup, relative paths are resolved against `asset_root`
+    pub file: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let cwd = env::current_dir().unwrap_or_default();
+        Self {
+            workspaces_root: cwd.join("hytale_workspaces"),
+            workspace: "HytaleGenerator Java".to_owned(),
+            asset_root: cwd.join("hytale_assets"),
+            file: Some(
+                Path::new("HytaleGenerator")
+                    .join("Biomes")
+                    .join("Basic.json"),
+            ),
+        }
+    }
+}
+
+impl Settings {
+    pub const USAGE: &str =
+        "Usage: HyNodeEditor [--workspaces <dir>] [--workspace <name>] [--assets <dir>] [file]
+       HyNodeEditor validate [--workspaces <dir>] [workspace dir...]";
+
+    /// Location of the settings file inside of the platform specific config directory
+    pub fn settings_path() -> Option<PathBuf> {
+        let config = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        };
+
+        config.map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(SETTINGS_FILE))
+    }
+
+    /// Loads the persisted settings, falling back to the defaults if there are none (or they are broken)
+    pub fn load() -> Self {
+        let Some(path) = Self::settings_path() else {
+            return Self::default();
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|err| {
+                log::warn!("Ignoring invalid settings file {}: {}", path.display(), err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::settings_path()
+            .ok_or_else(|| anyhow::Error::msg("No config directory available"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Overrides the settings with command line arguments (without the program name)
+    pub fn apply_args<I>(&mut self, args: I) -> Result<(), SettingsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        for arg in self.apply_options(args)? {
+            self.file = Some(env::current_dir().unwrap_or_default().join(arg));
+        }
+
+        Ok(())
+    }
+
+    /// Overrides the settings with the `--` options and returns the remaining arguments
+    pub fn apply_options<I>(&mut self, args: I) -> Result<Vec<String>, SettingsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut remaining = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| SettingsError::MissingValue(arg.clone()))
+            };
+
+            match arg.as_str() {
+                "--workspaces" => self.workspaces_root = value()?.into(),
+                "--workspace" => self.workspace = value()?,
+                "--assets" => self.asset_root = value()?.into(),
+                _ if arg.starts_with("--") => return Err(SettingsError::UnknownArgument(arg)),
+                _ => remaining.push(arg),
+            }
+        }
+
+        Ok(remaining)
+    }
+
+    /// Copies the values that differ between `before` and `after`, keeping all others
+    pub fn apply_changes(&mut self, before: &Settings, after: &Settings) {
+        if before.workspaces_root != after.workspaces_root {
+            self.workspaces_root = after.workspaces_root.clone();
+        }
+        if before.workspace != after.workspace {
+            self.workspace = after.workspace.clone();
+        }
+        if before.asset_root != after.asset_root {
+            self.asset_root = after.asset_root.clone();
+        }
+        if before.file != after.file {
+            self.file = after.file.clone();
+        }
+    }
+
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.file.as_ref().map(|file| self.asset_root.join(file))
+    }
+
+    /// Lists the names of all workspace folders inside of the workspaces root
+    pub fn available_workspaces(&self) -> Vec<String> {
+        let mut names = fs::read_dir(&self.workspaces_root)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().join("_Workspace.json").is_file())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::settings::{Settings, SettingsError};
+
+    #[test]
+    pub fn command_line_overrides() {
+        let mut settings = Settings::default();
+        settings
+            .apply_args(
+                [
+                    "--workspaces",
+                    "/ws",
+                    "--workspace",
+                    "ScriptableBrushes",
+                    "--assets",
+                    "/assets",
+                ]
+                .map(String::from),
+            )
+            .unwrap();
+
+        assert_eq!(settings.workspaces_root, PathBuf::from("/ws"));
+        assert_eq!(settings.workspace, "ScriptableBrushes");
+        assert_eq!(settings.asset_root, PathBuf::from("/assets"));
+
+        // Only values changed afterwards end up in the stored settings
+        let mut stored = Settings::default();
+        let mut changed = settings.clone();
+        changed.workspace = "Other".to_owned();
+        changed.file = None;
+        stored.apply_changes(&settings, &changed);
+        assert_eq!(stored.workspaces_root, Settings::default().workspaces_root);
+        assert_eq!(stored.workspace, "Other");
+        assert_eq!(stored.file, None);
+
+        assert!(matches!(
+            settings.apply_args(["--assets".to_owned()]),
+            Err(SettingsError::MissingValue(_))
+        ));
+        assert!(matches!(
+            settings.apply_args(["--unknown".to_owned()]),
+            Err(SettingsError::UnknownArgument(_))
+        ));
+    }
+}
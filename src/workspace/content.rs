@@ -1,7 +1,24 @@
-use serde::{self, Deserialize, Deserializer, Serialize};
+use serde::{self, Deserialize, Deserializer, Serialize, Serializer, ser::SerializeMap};
 use serde_json::Value;
 
+/// Names accepted for the `Type` of the known content types
+const KNOWN_CONTENT_TYPES: &[&str] = &[
+    "SmallString",
+    "Enum",
+    "List",
+    "IntSlider",
+    "Bool",
+    "String",
+    "Checkbox",
+    "Int",
+    "Integer",
+    "Float",
+    "Object",
+];
+
+// The derived (de)serializers are only used for the known types, see the impls below
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 #[serde(rename_all = "PascalCase", tag = "Type", content = "Options")]
 pub enum ContentType {
     #[serde(rename_all = "PascalCase")]
@@ -73,6 +90,48 @@ pub enum ContentType {
         label: String,
         fields: Vec<ContentObjectFields>,
     },
+    /// A content type this version of the editor doesn't know (yet). The options are kept as they are
+    #[serde(skip)]
+    Unknown { type_name: String, options: Value },
+}
+
+impl<'de> Deserialize<'de> for ContentType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let type_name = value
+            .get("Type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| serde::de::Error::missing_field("Type"))?;
+
+        if KNOWN_CONTENT_TYPES.contains(&type_name) {
+            ContentType::deserialize(value).map_err(serde::de::Error::custom)
+        } else {
+            Ok(ContentType::Unknown {
+                type_name: type_name.to_owned(),
+                options: value.get("Options").cloned().unwrap_or(Value::Null),
+            })
+        }
+    }
+}
+
+impl Serialize for ContentType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ContentType::Unknown { type_name, options } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("Type", type_name)?;
+                map.serialize_entry("Options", options)?;
+                map.end()
+            }
+            known => ContentType::serialize(known, serializer),
+        }
+    }
 }
 
 /// Just a helper type since the existing content types are more verbose than what we need for simple casts
@@ -85,6 +144,8 @@ pub enum ValueType {
     String,
     List,
     Enum,
+    /// Edited as raw JSON
+    Unknown,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -129,21 +190,20 @@ impl ContentType {
                 (default.clone().map(Value::from), ValueType::Enum)
             }
             ContentType::List { .. } => (None, ValueType::List),
-            ContentType::IntSlider { default, .. } => {
-                (default.clone().map(Value::from), ValueType::Int)
-            }
+            ContentType::IntSlider { default, .. } => ((*default).map(Value::from), ValueType::Int),
             ContentType::Bool { default_value, .. } => {
-                (default_value.clone().map(Value::from), ValueType::Boolean)
+                ((*default_value).map(Value::from), ValueType::Boolean)
             }
             ContentType::String { .. } => (None, ValueType::String),
             ContentType::Checkbox { default, .. } => {
-                (default.clone().map(Value::from), ValueType::Boolean)
-            }
-            ContentType::Int { default, .. } => (default.clone().map(Value::from), ValueType::Int),
-            ContentType::Float { default, .. } => {
-                (default.clone().map(Value::from), ValueType::Float)
+                ((*default).map(Value::from), ValueType::Boolean)
             }
+            ContentType::Int { default, .. } => ((*default).map(Value::from), ValueType::Int),
+            ContentType::Float { default, .. } => ((*default).map(Value::from), ValueType::Float),
             ContentType::Object { .. } => (None, ValueType::Object),
+            ContentType::Unknown { options, .. } => {
+                (options.get("Default").cloned(), ValueType::Unknown)
+            }
         };
 
         (val.0.unwrap_or(Value::Null), val.1)
@@ -161,8 +221,95 @@ impl ContentType {
             ContentType::Int { label, width, .. } => (label, *width),
             ContentType::Float { label, width, .. } => (label, *width),
             ContentType::Object { label, fields: _ } => (label, None),
+            ContentType::Unknown { type_name, options } => {
+                return (
+                    options
+                        .get("Label")
+                        .and_then(Value::as_str)
+                        .unwrap_or(type_name),
+                    options
+                        .get("Width")
+                        .and_then(Value::as_u64)
+                        .map(|width| width as u32),
+                );
+            }
         };
 
         (label, width)
     }
+
+    /// Content type used to edit a single element of a list with the given `array_element_type`.
+    /// Element types without a matching widget are edited as raw JSON
+    pub fn list_element(type_name: &str) -> ContentType {
+        match type_name {
+            "String" | "SmallString" => ContentType::SmallString {
+                label: String::new(),
+                default: None,
+                width: None,
+            },
+            "Int" | "Integer" => ContentType::Int {
+                label: String::new(),
+                width: None,
+                default: None,
+                min: None,
+                max: None,
+            },
+            "Float" | "Double" => ContentType::Float {
+                label: String::new(),
+                width: None,
+                default: None,
+                min: None,
+                max: None,
+            },
+            "Bool" | "Boolean" | "Checkbox" => ContentType::Bool {
+                label: String::new(),
+                width: None,
+                default_value: None,
+            },
+            _ => ContentType::Unknown {
+                type_name: type_name.to_owned(),
+                options: Value::Null,
+            },
+        }
+    }
+
+    /// Names of all unknown content types, including the ones nested in objects
+    pub fn unknown_types(&self) -> Vec<&str> {
+        match self {
+            ContentType::Unknown { type_name, .. } => vec![type_name.as_str()],
+            ContentType::Object { fields, .. } => fields
+                .iter()
+                .flat_map(|field| field.options.unknown_types())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::workspace::content::{Content, ContentType};
+
+    #[test]
+    pub fn unknown_content_round_trip() {
+        let raw = json!({
+            "Id": "Curve",
+            "Type": "CurveEditor",
+            "Options": { "Label": "Curve", "Points": [[0, 0], [1, 1]] }
+        });
+
+        let content = serde_json::from_value::<Content>(raw.clone()).unwrap();
+        assert!(matches!(
+            &content.options,
+            ContentType::Unknown { type_name, .. } if type_name == "CurveEditor"
+        ));
+        assert_eq!(content.options.get_common().0, "Curve");
+        assert_eq!(serde_json::to_value(&content).unwrap(), raw);
+
+        // Known types with broken options are still errors
+        let broken = json!({ "Id": "Value", "Type": "Float", "Options": { "Label": 5 } });
+        assert!(serde_json::from_value::<Content>(broken).is_err());
+    }
 }
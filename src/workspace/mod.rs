@@ -2,51 +2,239 @@ use crate::workspace::{nodes::NodeDescription, schemas::WorkspaceSchema};
 use std::{
     fs::{self, DirEntry},
     io,
+    num::NonZeroUsize,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
 };
 
 pub mod color;
 pub mod content;
 pub mod nodes;
+pub mod registry;
 pub mod schemas;
+pub mod validate;
+pub mod watcher;
 pub mod workspace;
 
 #[derive(thiserror::Error, Debug)]
 pub enum WorkspacePaserError {
     #[error("Failed to read the file {0}: {1}")]
     ReadError(PathBuf, anyhow::Error),
+    #[error("Failed to parse {} at line {line} column {column}: {message}", .path.display())]
+    ParseError {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
 }
 
+/// A problem with a single file found while loading a workspace leniently
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// Line and column for JSON syntax and format errors
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn from_error(path: PathBuf, err: &anyhow::Error) -> Self {
+        let parse_error = err.chain().find_map(|cause| {
+            if let Some(WorkspacePaserError::ParseError {
+                line,
+                column,
+                message,
+                ..
+            }) = cause.downcast_ref()
+            {
+                return Some(((*line, *column), message.clone()));
+            }
+
+            // load_workspace wraps the serde error into an io::Error
+            let json = cause.downcast_ref::<serde_json::Error>().or_else(|| {
+                cause
+                    .downcast_ref::<io::Error>()
+                    .and_then(io::Error::get_ref)
+                    .and_then(|inner| inner.downcast_ref::<serde_json::Error>())
+            })?;
+            Some(((json.line(), json.column()), json_error_message(json)))
+        });
+
+        match parse_error {
+            Some((location, message)) => Self {
+                path,
+                location: Some(location),
+                message,
+            },
+            None => Self {
+                path,
+                location: None,
+                message: format!("{:#}", err),
+            },
+        }
+    }
+}
+
+/// The serde message without the location suffix, as the location is reported separately
+pub fn json_error_message(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    let suffix = format!(" at line {} column {}", err.line(), err.column());
+    message
+        .strip_suffix(&suffix)
+        .map(str::to_owned)
+        .unwrap_or(message)
+}
+
+/// Number of descriptor files found and parsed so far, shared with the UI while loading in the background
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    found: AtomicUsize,
+    parsed: AtomicUsize,
+}
+
+impl LoadProgress {
+    /// Parsed and found files. The number of found files grows while further workspaces are scanned
+    pub fn files(&self) -> (usize, usize) {
+        (
+            self.parsed.load(Ordering::Relaxed),
+            self.found.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Loads all node descriptions, failing on the first file that can't be loaded.
+/// The editor loads through the registry, this strict variant is only used by the tests
+#[cfg(test)]
 pub fn load_descriptions(path: &Path) -> anyhow::Result<Vec<NodeDescription>> {
-    let entries = fs::read_dir(path)?;
     let mut schemas = Vec::new();
-    for entry in entries.flatten() {
-        load_descriptions_recurse(&entry, &mut schemas)?;
+    load_descriptions_recurse(
+        path,
+        &mut schemas,
+        &LoadProgress::default(),
+        &mut |_, err| Err(err),
+    )?;
+    Ok(schemas.into_iter().map(|(_, schema)| schema).collect())
+}
+
+/// Loads every description that can be parsed. Files failing to load are skipped and reported as diagnostics.
+/// Content types unknown to the editor are reported as well
+pub fn load_descriptions_lenient(
+    path: &Path,
+    diagnostics: &mut Vec<Diagnostic>,
+    progress: &LoadProgress,
+) -> anyhow::Result<Vec<NodeDescription>> {
+    let mut schemas = Vec::new();
+    load_descriptions_recurse(path, &mut schemas, progress, &mut |path, err| {
+        log::warn!("Skipping {}: {:#}", path.display(), err);
+        diagnostics.push(Diagnostic::from_error(path, &err));
+        Ok(())
+    })?;
+
+    for (path, schema) in schemas.iter() {
+        for content in schema.content.iter() {
+            for type_name in content.options.unknown_types() {
+                diagnostics.push(Diagnostic {
+                    path: path.clone(),
+                    location: None,
+                    message: format!(
+                        "Unknown content type '{}' of '{}', edited as raw JSON",
+                        type_name, content.id
+                    ),
+                });
+            }
+        }
     }
 
-    Ok(schemas)
+    Ok(schemas.into_iter().map(|(_, schema)| schema).collect())
 }
 
+/// Collects all descriptor files below `dir` and parses them in parallel.
+/// Errors are reported in the order of the (sorted) files
 fn load_descriptions_recurse(
-    entry: &DirEntry,
-    schemas: &mut Vec<NodeDescription>,
+    dir: &Path,
+    schemas: &mut Vec<(PathBuf, NodeDescription)>,
+    progress: &LoadProgress,
+    on_error: &mut dyn FnMut(PathBuf, anyhow::Error) -> anyhow::Result<()>,
 ) -> anyhow::Result<()> {
-    let path = entry.path();
-    if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-        let entries = fs::read_dir(&path)?;
-        for entry in entries.flatten() {
-            load_descriptions_recurse(&entry, schemas)?;
+    let mut files = Vec::new();
+    collect_description_files(dir, &mut files, on_error)?;
+    progress.found.fetch_add(files.len(), Ordering::Relaxed);
+
+    let results = parse_descriptions(&files, progress);
+    for (path, result) in files.into_iter().zip(results) {
+        match result {
+            Ok(description) => schemas.push((path, description)),
+            Err(err) if err.is::<WorkspacePaserError>() => on_error(path, err)?,
+            Err(err) => on_error(
+                path.clone(),
+                WorkspacePaserError::ReadError(path, err).into(),
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_description_files(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    on_error: &mut dyn FnMut(PathBuf, anyhow::Error) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?.flatten().collect::<Vec<_>>();
+    entries.sort_by_key(DirEntry::path);
+
+    for entry in entries.into_iter() {
+        let path = entry.path();
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            if let Err(err) = collect_description_files(&path, files, on_error) {
+                on_error(path, err)?;
+            }
+        } else if !entry.file_name().eq_ignore_ascii_case("_Workspace.json") {
+            files.push(path);
         }
-    } else if !entry.file_name().eq_ignore_ascii_case("_Workspace.json") {
-        schemas.push(
-            NodeDescription::load_from_file(&path)
-                .map_err(|err| WorkspacePaserError::ReadError(path, err))?,
-        );
     }
 
     Ok(())
 }
 
+/// Parses the files on all available cores, the results are in the same order as the files
+fn parse_descriptions(
+    files: &[PathBuf],
+    progress: &LoadProgress,
+) -> Vec<anyhow::Result<NodeDescription>> {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| {
+                            let result = NodeDescription::load_from_file(path);
+                            progress.parsed.fetch_add(1, Ordering::Relaxed);
+                            result
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
 pub fn load_workspace(path: &Path) -> io::Result<WorkspaceSchema> {
     let mut ws_path = path.to_path_buf();
     ws_path.push("_Workspace.json");
@@ -57,17 +245,174 @@ pub fn load_workspace(path: &Path) -> io::Result<WorkspaceSchema> {
         Ok(workspace) => Ok(workspace),
         Err(err) => {
             eprintln!("Failed to parse JSON schema at: {:?}", err);
-            Err(io::Error::new(io::ErrorKind::Other, err))
+            Err(io::Error::other(err))
         }
     }
 }
 
 #[cfg(test)]
-mod tests {
-    use egui::ahash::HashSet;
+pub(crate) mod tests {
+    use serde_json::json;
+
+    use crate::workspace::{
+        LoadProgress, load_descriptions, load_descriptions_lenient, load_workspace,
+        nodes::{NodeDescription, NodePin},
+        registry::{WorkspaceEntry, WorkspaceRegistry},
+        schemas::WorkspaceSchema,
+        workspace::Workspace,
+    };
+    use std::{env, fs, path::PathBuf, sync::Arc};
+
+    /// Small self contained workspace (Biome -> Density variants) used for round trip tests
+    pub fn synthetic_workspace() -> Workspace {
+        let schema = serde_json::from_value::<WorkspaceSchema>(json!({
+            "WorkspaceName": "Test",
+            "Roots": { "Biome": { "RootNodeType": "Biome", "MenuName": "Biome" } },
+            "NodeCategories": { "Density": ["ConstantDensity", "SumDensity"] },
+            "Variants": {
+                "Density": {
+                    "VariantFieldName": "Type",
+                    "Variants": {
+                        "Constant": "ConstantDensity",
+                        "Fixed": "ConstantDensity",
+                        "Sum": "SumDensity"
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let nodes = [
+            json!({
+                "Id": "Biome",
+                "Title": "Biome",
+                "Color": "Green",
+                "Content": [
+                    { "Id": "Name", "Type": "SmallString", "Options": { "Label": "Name" } },
+                    { "Id": "Seed", "Type": "Int", "Options": { "Label": "Seed" } },
+                    { "Id": "Enabled", "Type": "Checkbox", "Options": { "Label": "Enabled" } }
+                ],
+                "Outputs": [{ "Id": "Terrain", "Type": "Density", "Color": "Blue", "Multiple": false }],
+                "Schema": { "Terrain": { "Node": "Density", "Pin": "Terrain" } }
+            }),
+            json!({
+                "Id": "ConstantDensity",
+                "Title": "Constant",
+                "Color": "Blue",
+                "Content": [{ "Id": "Value", "Type": "Float", "Options": { "Label": "Value" } }],
+                "Inputs": [{ "Id": "Input", "Type": "Density", "Color": "Blue" }]
+            }),
+            json!({
+                "Id": "SumDensity",
+                "Title": "Sum",
+                "Color": "Blue",
+                "Inputs": [{ "Id": "Input", "Type": "Density", "Color": "Blue" }],
+                "Outputs": [{ "Id": "Inputs", "Type": "Density", "Color": "Blue" }],
+                "Schema": { "Inputs": { "Node": "Density", "Pin": "Inputs" } }
+            }),
+        ]
+        .into_iter()
+        .map(|node| serde_json::from_value::<NodeDescription>(node).unwrap())
+        .collect();
+
+        Workspace::construct(schema, nodes)
+    }
 
-    use crate::workspace::{load_descriptions, load_workspace, workspace::Workspace};
-    use std::{env, fs};
+    #[test]
+    pub fn lenient_descriptions() {
+        let dir = env::temp_dir().join(format!("hynode-lenient-{}", std::process::id()));
+        fs::create_dir_all(dir.join("Nested")).unwrap();
+        fs::write(
+            dir.join("Good.json"),
+            r#"{ "Id": "Good", "Title": "Good", "Color": "Red" }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("Nested").join("Broken.json"),
+            "// Comment before the JSON\n{\n  \"Id\": \"Broken\",\n  \"Title\": 5\n}",
+        )
+        .unwrap();
+
+        let mut diagnostics = Vec::new();
+        let progress = LoadProgress::default();
+        let descriptions = load_descriptions_lenient(&dir, &mut diagnostics, &progress).unwrap();
+        assert!(load_descriptions(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(descriptions[0].id, "Good");
+        assert_eq!(progress.files(), (2, 2));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].path.ends_with("Nested/Broken.json"));
+        assert_eq!(diagnostics[0].location.map(|loc| loc.0), Some(4));
+        assert!(diagnostics[0].message.contains("invalid type"));
+    }
+
+    #[test]
+    pub fn registry_workspace_lookup() {
+        let entry = |directory: &str, name: &str| {
+            let mut workspace = synthetic_workspace();
+            workspace.workspace.workspace_name = name.to_owned();
+            WorkspaceEntry {
+                directory: directory.to_owned(),
+                path: PathBuf::from(directory),
+                workspace: Arc::new(workspace),
+                diagnostics: Vec::new(),
+            }
+        };
+
+        let registry = WorkspaceRegistry::from_entries(vec![
+            entry("HytaleGenerator Java", "HytaleGenerator"),
+            entry("ScriptableBrushes", "ScriptableBrushes"),
+        ]);
+
+        let found = registry
+            .find_for_workspace_id("HytaleGenerator - Biome")
+            .unwrap();
+        assert_eq!(found.directory, "HytaleGenerator Java");
+        let found = registry.find_for_workspace_id("ScriptableBrushes").unwrap();
+        assert_eq!(found.directory, "ScriptableBrushes");
+        assert!(registry.find_for_workspace_id("Unknown - Biome").is_none());
+        assert!(registry.get_by_directory("ScriptableBrushes").is_some());
+    }
+
+    #[test]
+    pub fn input_for_pin() {
+        let parent = serde_json::from_value::<NodeDescription>(json!({
+            "Id": "Parent",
+            "Title": "Parent",
+            "Color": "Blue",
+            "Outputs": [{ "Id": "Inputs", "Type": "Density", "Color": "Blue" }]
+        }))
+        .unwrap();
+        let pin = NodePin {
+            node: "Density".to_owned(),
+            pin: "Inputs".to_owned(),
+        };
+        let child = |inputs| {
+            serde_json::from_value::<NodeDescription>(json!({
+                "Id": "Child",
+                "Title": "Child",
+                "Color": "Blue",
+                "Inputs": inputs
+            }))
+            .unwrap()
+        };
+
+        // An input named like the pin but of another type loses against the matching type
+        let mismatched = child(json!([
+            { "Id": "Inputs", "Type": "Curve", "Color": "Red" },
+            { "Id": "Input", "Type": "Density", "Color": "Blue" }
+        ]));
+        assert_eq!(mismatched.get_input_for(&pin, &parent.outputs[0]), Some(1));
+
+        // The pin id decides between inputs of the same type
+        let ambiguous = child(json!([
+            { "Id": "Input", "Type": "Density", "Color": "Blue" },
+            { "Id": "Inputs", "Type": "Density", "Color": "Blue" }
+        ]));
+        assert_eq!(ambiguous.get_input_for(&pin, &parent.outputs[0]), Some(1));
+    }
 
     #[test]
     pub fn loading_descriptions() -> anyhow::Result<()> {
@@ -132,7 +477,7 @@ mod tests {
         for entry in entries.flatten() {
             let sub_path = entry.path();
             // Read the directory
-            let mut workspace = load_workspace(&sub_path).expect("Failed to load workspaces");
+            let workspace = load_workspace(&sub_path).expect("Failed to load workspaces");
             println!("{:?}", workspace)
         }
     }
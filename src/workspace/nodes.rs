@@ -4,7 +4,10 @@ use serde::{self, Deserialize, Serialize};
 
 use serde_aux::prelude::*;
 
-use crate::workspace::{color::ColorValue, content::Content, workspace::Workspace};
+use crate::workspace::{
+    WorkspacePaserError, color::ColorValue, content::Content, json_error_message,
+    workspace::Workspace,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
@@ -59,11 +62,29 @@ fn default_connector_multiple() -> bool {
 
 impl NodeDescription {
     pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
-        let content = fs::read_to_string(&path)?;
+        let content = fs::read_to_string(path)?;
         let start = content
-            .find(|c| c == '{')
+            .find('{')
             .ok_or(anyhow::Error::msg("No start of JSON found"))?;
-        Ok(serde_json::from_str::<NodeDescription>(&content[start..])?)
+
+        serde_json::from_str::<NodeDescription>(&content[start..]).map_err(|err| {
+            // Locations are relative to the start of the JSON, not the file
+            let prefix = &content[..start];
+            let line = err.line() + prefix.matches('\n').count();
+            let column = if err.line() == 1 {
+                err.column() + prefix.len() - prefix.rfind('\n').map_or(0, |pos| pos + 1)
+            } else {
+                err.column()
+            };
+
+            WorkspacePaserError::ParseError {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message: json_error_message(&err),
+            }
+            .into()
+        })
     }
 
     pub fn get_connector<'a>(&'a self, key: &str) -> Option<(usize, &'a Connector)> {
@@ -77,6 +98,34 @@ impl NodeDescription {
         }
     }
 
+    /// Finds the input a child node is connected with when it is attached to `output` of its parent.
+    /// Inputs are matched by the connector type first, the id of the schema pin only decides between
+    /// several inputs of that type. Then the node type of the pin is tried, nodes with a single input
+    /// always use that one
+    pub fn get_input_for(&self, pin: &NodePin, output: &Connector) -> Option<usize> {
+        let typed = || {
+            self.inputs
+                .iter()
+                .enumerate()
+                .filter(|(_, input)| input.typ == output.typ)
+        };
+        typed()
+            .find(|(_, input)| input.id == pin.pin)
+            .or_else(|| typed().next())
+            .map(|(index, _)| index)
+            .or_else(|| self.inputs.iter().position(|input| input.typ == pin.node))
+            .or_else(|| (self.inputs.len() == 1).then_some(0))
+    }
+
+    /// Finds the schema key (and pin) that is connected to the given output connector.
+    /// This is the reverse lookup of [`NodeDescription::get_connector`]
+    pub fn get_schema_pin<'a>(&'a self, connector_id: &str) -> Option<(&'a str, &'a NodePin)> {
+        self.schema.iter().find_map(|(key, schema)| match schema {
+            SchemaObject::Pin(pin) if pin.pin == connector_id => Some((key.as_str(), pin)),
+            _ => None,
+        })
+    }
+
     pub fn get_pin<'a>(&'a self, key: &str) -> Option<&'a NodePin> {
         if let Some(SchemaObject::Pin(pin)) = self.schema.get(key) {
             Some(pin)
@@ -97,16 +146,14 @@ impl NodeDescription {
     where
         R: FnOnce(&str) -> Option<&'c str>,
     {
-        self.get_pin(key).and_then(|pin| {
-            if let Some(variant) = workspace.workspace.variants.get(&pin.node) {
-                resolver(&variant.variant_field_name)
-                    .and_then(|res| variant.variants.get(res))
-                    .and_then(|vaiant_name| {
-                        workspace.nodes.iter().find(|desc| desc.id.eq(vaiant_name))
-                    })
-            } else {
-                workspace.nodes.iter().find(|desc| desc.id == pin.node)
-            }
-        })
+        self.get_pin(key)
+            .and_then(|pin| {
+                if let Some(field_name) = workspace.get_variant_field(&pin.node) {
+                    resolver(field_name).and_then(|res| workspace.resolve_variant(&pin.node, res))
+                } else {
+                    workspace.get_description(&pin.node)
+                }
+            })
+            .map(|desc| desc.as_ref())
     }
 }
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::workspace::{
+    Diagnostic, LoadProgress, load_descriptions_lenient, load_workspace, workspace::Workspace,
+};
+
+/// A workspace loaded from one of the folders in the workspaces root
+#[derive(Debug, Clone)]
+pub struct WorkspaceEntry {
+    /// Name of the folder (e.g. "HytaleGenerator Java")
+    pub directory: String,
+    pub path: PathBuf,
+    pub workspace: Arc<Workspace>,
+    /// Descriptor files that were skipped while loading
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// All workspaces available in the workspaces root, indexed by their `WorkspaceName` and folder
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceRegistry {
+    entries: Vec<WorkspaceEntry>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_directory: HashMap<String, usize>,
+    /// Workspaces that couldn't be loaded at all
+    failures: Vec<Diagnostic>,
+}
+
+impl WorkspaceRegistry {
+    /// Loads every folder in the root that contains a `_Workspace.json`.
+    /// Workspaces failing to load are logged and skipped so they don't take the others with them
+    pub fn load(root: &Path, progress: &LoadProgress) -> anyhow::Result<Self> {
+        let mut dirs = fs::read_dir(root)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.join("_Workspace.json").is_file())
+            .collect::<Vec<_>>();
+        dirs.sort();
+
+        let mut entries = Vec::with_capacity(dirs.len());
+        let mut failures = Vec::new();
+        for path in dirs.into_iter() {
+            match Self::load_entry(&path, progress) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => {
+                    log::error!("Failed to load workspace {}: {:#}", path.display(), err);
+                    failures.push(Diagnostic::from_error(path.join("_Workspace.json"), &err));
+                }
+            }
+        }
+
+        let mut registry = Self::from_entries(entries);
+        registry.failures = failures;
+        Ok(registry)
+    }
+
+    pub fn load_entry(path: &Path, progress: &LoadProgress) -> anyhow::Result<WorkspaceEntry> {
+        let schema = load_workspace(path)?;
+        let mut diagnostics = Vec::new();
+        let descriptions = load_descriptions_lenient(path, &mut diagnostics, progress)?;
+
+        Ok(WorkspaceEntry {
+            directory: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: path.to_path_buf(),
+            workspace: Arc::new(Workspace::construct(schema, descriptions)),
+            diagnostics,
+        })
+    }
+
+    pub fn from_entries(entries: Vec<WorkspaceEntry>) -> Self {
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_directory = HashMap::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            by_name
+                .entry(entry.workspace.workspace.workspace_name.clone())
+                .or_default()
+                .push(index);
+            by_directory.insert(entry.directory.clone(), index);
+        }
+
+        Self {
+            entries,
+            by_name,
+            by_directory,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Replaces the workspace loaded from the same folder (e.g. after its files changed)
+    pub fn replace_entry(&mut self, entry: WorkspaceEntry) {
+        let mut entries = std::mem::take(&mut self.entries);
+        match entries
+            .iter_mut()
+            .find(|existing| existing.directory == entry.directory)
+        {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+
+        let failures = std::mem::take(&mut self.failures);
+        *self = Self::from_entries(entries);
+        self.failures = failures;
+    }
+
+    /// Workspace folders that failed to load entirely
+    pub fn failures(&self) -> &[Diagnostic] {
+        &self.failures
+    }
+
+    /// All problems found while loading the workspaces
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.failures.iter().chain(
+            self.entries
+                .iter()
+                .flat_map(|entry| entry.diagnostics.iter()),
+        )
+    }
+
+    pub fn entries(&self) -> &[WorkspaceEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_by_directory(&self, directory: &str) -> Option<&WorkspaceEntry> {
+        self.by_directory
+            .get(directory)
+            .map(|index| &self.entries[*index])
+    }
+
+    /// Finds the workspace an asset belongs to based on its `$WorkspaceID` (e.g. "HytaleGenerator - Biome").
+    /// The part before the separator is matched against the `WorkspaceName` first and the folder name second.
+    /// If multiple workspaces match, the one containing a root for the part after the separator is preferred.
+    pub fn find_for_workspace_id(&self, workspace_id: &str) -> Option<&WorkspaceEntry> {
+        let (name, root_key) = workspace_id
+            .split_once(" - ")
+            .map(|(name, key)| (name.trim(), Some(key.trim())))
+            .unwrap_or((workspace_id.trim(), None));
+
+        let mut candidates = self.by_name.get(name).cloned().unwrap_or_default();
+        if candidates.is_empty() {
+            candidates = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.directory.starts_with(name))
+                .map(|(index, _)| index)
+                .collect();
+        }
+
+        let has_root = |index: &&usize| {
+            root_key.is_some_and(|key| {
+                self.entries[**index]
+                    .workspace
+                    .workspace
+                    .roots
+                    .iter()
+                    .any(|(id, root)| id == key || root.menu_name == key)
+            })
+        };
+
+        candidates
+            .iter()
+            .find(has_root)
+            .or_else(|| candidates.first())
+            .map(|index| &self.entries[*index])
+    }
+}
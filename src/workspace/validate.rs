@@ -0,0 +1,263 @@
+use std::{collections::HashSet, path::Path};
+
+use crate::workspace::{
+    LoadProgress,
+    nodes::SchemaObject,
+    registry::{WorkspaceEntry, WorkspaceRegistry},
+    workspace::Workspace,
+};
+
+/// An inconsistency between the workspace definition and its node descriptors
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    #[error("Variant {variant} maps {key} to the missing descriptor {descriptor}")]
+    UnresolvedVariant {
+        variant: String,
+        key: String,
+        descriptor: String,
+    },
+    #[error("Schema entry {key} of {descriptor} refers to the missing node or variant {node}")]
+    MissingPinNode {
+        descriptor: String,
+        key: String,
+        node: String,
+    },
+    #[error("Schema entry {key} of {descriptor} refers to the missing output pin {pin}")]
+    MissingPin {
+        descriptor: String,
+        key: String,
+        pin: String,
+    },
+    #[error("Category {category} contains the missing node {node}")]
+    MissingCategoryNode { category: String, node: String },
+    #[error("Root {root} uses the missing node type {node}")]
+    MissingRootNode { root: String, node: String },
+    #[error("Descriptor id {0} is defined more than once")]
+    DuplicateDescriptor(String),
+}
+
+/// Checks that everything the workspace definition and the descriptors refer to actually exists.
+/// The issues are sorted so the output is stable between runs
+pub fn validate(workspace: &Workspace) -> Vec<ValidationIssue> {
+    let schema = &workspace.workspace;
+    let mut issues = Vec::new();
+
+    let mut ids = HashSet::with_capacity(workspace.nodes.len());
+    let mut duplicates = HashSet::new();
+    for node in workspace.nodes.iter() {
+        if !ids.insert(node.id.as_str()) && duplicates.insert(node.id.as_str()) {
+            issues.push(ValidationIssue::DuplicateDescriptor(node.id.clone()));
+        }
+    }
+
+    for (variant_name, variant) in sorted(schema.variants.iter()) {
+        for (key, descriptor) in sorted(variant.variants.iter()) {
+            if !ids.contains(descriptor.as_str()) {
+                issues.push(ValidationIssue::UnresolvedVariant {
+                    variant: variant_name.clone(),
+                    key: key.clone(),
+                    descriptor: descriptor.clone(),
+                });
+            }
+        }
+    }
+
+    for node in workspace.nodes.iter() {
+        for (key, object) in sorted(node.schema.iter()) {
+            let SchemaObject::Pin(pin) = object else {
+                continue;
+            };
+
+            if !ids.contains(pin.node.as_str()) && !schema.variants.contains_key(&pin.node) {
+                issues.push(ValidationIssue::MissingPinNode {
+                    descriptor: node.id.clone(),
+                    key: key.clone(),
+                    node: pin.node.clone(),
+                });
+            }
+            if node.get_connector(key).is_none() {
+                issues.push(ValidationIssue::MissingPin {
+                    descriptor: node.id.clone(),
+                    key: key.clone(),
+                    pin: pin.pin.clone(),
+                });
+            }
+        }
+    }
+
+    for (category, nodes) in sorted(schema.node_categories.iter()) {
+        for node in nodes.iter().filter(|node| !ids.contains(node.as_str())) {
+            issues.push(ValidationIssue::MissingCategoryNode {
+                category: category.clone(),
+                node: node.clone(),
+            });
+        }
+    }
+
+    for (root_name, root) in sorted(schema.roots.iter()) {
+        if !ids.contains(root.root_node_type.as_str()) {
+            issues.push(ValidationIssue::MissingRootNode {
+                root: root_name.clone(),
+                node: root.root_node_type.clone(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Headless check of workspace folders, returns the process exit code.
+/// Without any folders, all workspaces inside of `workspaces_root` are checked
+pub fn run_command(workspaces_root: &Path, folders: &[String]) -> i32 {
+    let progress = LoadProgress::default();
+    let mut problems = 0;
+
+    if folders.is_empty() {
+        let registry = match WorkspaceRegistry::load(workspaces_root, &progress) {
+            Ok(registry) => registry,
+            Err(err) => {
+                eprintln!("Failed to read {}: {:#}", workspaces_root.display(), err);
+                return 2;
+            }
+        };
+
+        for failure in registry.failures() {
+            println!(
+                "{}: failed to load: {}",
+                failure.path.display(),
+                failure.message
+            );
+            problems += 1;
+        }
+        for entry in registry.entries() {
+            problems += report(entry);
+        }
+    } else {
+        for path in folders.iter().map(Path::new) {
+            match WorkspaceRegistry::load_entry(path, &progress) {
+                Ok(entry) => problems += report(&entry),
+                Err(err) => {
+                    println!("{}: failed to load: {:#}", path.display(), err);
+                    problems += 1;
+                }
+            }
+        }
+    }
+
+    if problems == 0 { 0 } else { 1 }
+}
+
+/// Prints the diagnostics and validation issues of a loaded workspace, returns their number
+fn report(entry: &WorkspaceEntry) -> usize {
+    let issues = validate(&entry.workspace);
+    println!(
+        "{}: {} descriptors, {} problems",
+        entry.path.display(),
+        entry.workspace.nodes.len(),
+        entry.diagnostics.len() + issues.len()
+    );
+    for diagnostic in entry.diagnostics.iter() {
+        match diagnostic.location {
+            Some((line, column)) => println!(
+                "  {}:{}:{}: {}",
+                diagnostic.path.display(),
+                line,
+                column,
+                diagnostic.message
+            ),
+            None => println!("  {}: {}", diagnostic.path.display(), diagnostic.message),
+        }
+    }
+    for issue in issues.iter() {
+        println!("  {}", issue);
+    }
+
+    entry.diagnostics.len() + issues.len()
+}
+
+/// Sorts the entries of a map by key
+fn sorted<'a, K: Ord, V>(iter: impl Iterator<Item = (&'a K, &'a V)>) -> Vec<(&'a K, &'a V)> {
+    let mut items = iter.collect::<Vec<_>>();
+    items.sort_by(|a, b| a.0.cmp(b.0));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::workspace::{
+        nodes::NodeDescription,
+        tests::synthetic_workspace,
+        validate::{ValidationIssue, validate},
+        workspace::Workspace,
+    };
+
+    #[test]
+    pub fn validate_workspace() {
+        let workspace = synthetic_workspace();
+        assert_eq!(validate(&workspace), Vec::new());
+
+        let mut schema = workspace.workspace.clone();
+        schema
+            .variants
+            .get_mut("Density")
+            .unwrap()
+            .variants
+            .insert("Noise".to_owned(), "NoiseDensity".to_owned());
+        schema
+            .node_categories
+            .insert("Misc".to_owned(), vec!["Missing".to_owned()]);
+        schema.roots.get_mut("Biome").unwrap().root_node_type = "Zone".to_owned();
+
+        let mut nodes = workspace
+            .nodes
+            .iter()
+            .map(|node| node.as_ref().clone())
+            .collect::<Vec<_>>();
+        nodes.push(
+            serde_json::from_value::<NodeDescription>(json!({
+                "Id": "SumDensity",
+                "Title": "Sum",
+                "Color": "Blue",
+                "Outputs": [{ "Id": "Inputs", "Type": "Density", "Color": "Blue" }],
+                "Schema": {
+                    "Inputs": { "Node": "Curve", "Pin": "Inputs" },
+                    "Other": { "Node": "Density", "Pin": "Other" }
+                }
+            }))
+            .unwrap(),
+        );
+
+        let issues = validate(&Workspace::construct(schema, nodes));
+        assert_eq!(
+            issues,
+            vec![
+                ValidationIssue::DuplicateDescriptor("SumDensity".to_owned()),
+                ValidationIssue::UnresolvedVariant {
+                    variant: "Density".to_owned(),
+                    key: "Noise".to_owned(),
+                    descriptor: "NoiseDensity".to_owned(),
+                },
+                ValidationIssue::MissingPinNode {
+                    descriptor: "SumDensity".to_owned(),
+                    key: "Inputs".to_owned(),
+                    node: "Curve".to_owned(),
+                },
+                ValidationIssue::MissingPin {
+                    descriptor: "SumDensity".to_owned(),
+                    key: "Other".to_owned(),
+                    pin: "Other".to_owned(),
+                },
+                ValidationIssue::MissingCategoryNode {
+                    category: "Misc".to_owned(),
+                    node: "Missing".to_owned(),
+                },
+                ValidationIssue::MissingRootNode {
+                    root: "Biome".to_owned(),
+                    node: "Zone".to_owned(),
+                },
+            ]
+        );
+    }
+}
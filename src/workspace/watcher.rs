@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::workspace::{
+    LoadProgress,
+    registry::{WorkspaceEntry, WorkspaceRegistry},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Modification time and size of every file below a directory
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// Watches a workspace folder by polling it and reloads the workspace on a background thread whenever
+/// a file is added, removed or modified. The thread stops once the watcher is dropped
+pub struct WorkspaceWatcher {
+    stop: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<anyhow::Result<WorkspaceEntry>>,
+}
+
+impl WorkspaceWatcher {
+    /// `on_reload` is called from the watcher thread after a reloaded workspace is available
+    pub fn spawn(path: PathBuf, on_reload: impl Fn() + Send + 'static) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let thread_stop = stop.clone();
+        thread::spawn(move || {
+            let mut previous = snapshot(&path);
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+
+                let current = snapshot(&path);
+                if current == previous {
+                    continue;
+                }
+                previous = current;
+
+                log::info!("{} changed, reloading", path.display());
+                let entry = WorkspaceRegistry::load_entry(&path, &LoadProgress::default());
+                if sender.send(entry).is_err() {
+                    break;
+                }
+                on_reload();
+            }
+        });
+
+        Self { stop, receiver }
+    }
+
+    /// The most recent reload since the last call, if any
+    pub fn poll(&self) -> Option<anyhow::Result<WorkspaceEntry>> {
+        self.receiver.try_iter().last()
+    }
+}
+
+impl Drop for WorkspaceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn snapshot(dir: &Path) -> Snapshot {
+    let mut files = HashMap::new();
+    snapshot_recurse(dir, &mut files);
+    files
+}
+
+fn snapshot_recurse(dir: &Path, files: &mut Snapshot) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            snapshot_recurse(&entry.path(), files);
+        } else {
+            files.insert(entry.path(), (metadata.modified().ok(), metadata.len()));
+        }
+    }
+}
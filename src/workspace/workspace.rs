@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::workspace::{color::ColorValue, nodes::NodeDescription, schemas::WorkspaceSchema};
 
@@ -9,60 +9,201 @@ pub struct NodeGroup {
     pub nodes: Vec<usize>,
 }
 
+/// Lookup tables of a single entry in `Variants`, all descriptors are indices into [`Workspace::nodes`]
+#[derive(Debug, Clone, Default)]
+struct VariantIndex {
+    field_name: String,
+    by_key: HashMap<String, usize>,
+    /// Descriptor id -> variant key
+    by_descriptor: HashMap<String, String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Workspace {
     pub workspace: WorkspaceSchema,
     pub groups: Vec<NodeGroup>,
-    pub nodes: Vec<NodeDescription>,
+    /// Shared with the editor nodes, so graphs don't borrow from the workspace
+    pub nodes: Vec<Arc<NodeDescription>>,
+    /// Descriptor id -> index into `nodes`. The first descriptor wins if an id is defined multiple times
+    by_id: HashMap<String, usize>,
+    variants: HashMap<String, VariantIndex>,
+    /// Category name -> indices into `nodes` of all existing nodes listed in it
+    categories: HashMap<String, Vec<usize>>,
 }
 
 impl Workspace {
     pub fn construct(schema: WorkspaceSchema, nodes: Vec<NodeDescription>) -> Workspace {
-        let mut node_map = nodes
+        let mut by_id = HashMap::with_capacity(nodes.len());
+        for (index, node) in nodes.iter().enumerate() {
+            by_id.entry(node.id.clone()).or_insert(index);
+        }
+
+        let variants = schema
+            .variants
             .iter()
-            .enumerate()
-            .map(|node| (node.1.id.clone(), (node.0, node.1)))
-            .collect::<HashMap<_, _>>();
-        let mut groups = Vec::with_capacity(schema.node_categories.len() + 1);
-
-        schema.node_categories.iter().for_each(|category| {
-            let nodes = category
-                .1
+            .map(|(name, variant)| {
+                let mut index = VariantIndex {
+                    field_name: variant.variant_field_name.clone(),
+                    ..Default::default()
+                };
+                for (key, descriptor) in variant.variants.iter() {
+                    if let Some(node) = by_id.get(descriptor) {
+                        index.by_key.insert(key.clone(), *node);
+                    }
+                    // Multiple keys may map to the same descriptor, pick one deterministically
+                    index
+                        .by_descriptor
+                        .entry(descriptor.clone())
+                        .and_modify(|existing| {
+                            if key < existing {
+                                *existing = key.clone();
+                            }
+                        })
+                        .or_insert_with(|| key.clone());
+                }
+                (name.clone(), index)
+            })
+            .collect();
+
+        let categories = schema
+            .node_categories
+            .iter()
+            .map(|(name, ids)| {
+                let nodes = ids.iter().filter_map(|id| by_id.get(id).copied()).collect();
+                (name.clone(), nodes)
+            })
+            .collect();
+
+        let mut workspace = Workspace {
+            workspace: schema,
+            groups: Vec::new(),
+            nodes: nodes.into_iter().map(Arc::new).collect(),
+            by_id,
+            variants,
+            categories,
+        };
+        workspace.groups = workspace.build_groups();
+        workspace
+    }
+
+    /// Groups the nodes by category for the menus. Each node is only listed in one group,
+    /// nodes without a category end up in "Uncategorized"
+    fn build_groups(&self) -> Vec<NodeGroup> {
+        let mut assigned = vec![false; self.nodes.len()];
+        let mut groups = Vec::with_capacity(self.categories.len() + 1);
+
+        for name in self.workspace.node_categories.keys() {
+            let nodes = self
+                .category_nodes(name)
                 .iter()
-                .filter_map(|node_id| node_map.remove(node_id))
+                .copied()
+                .filter(|index| !std::mem::replace(&mut assigned[*index], true))
                 .collect::<Vec<_>>();
 
             let mut color = Default::default();
-            if let Some(color2) = nodes.first().map(|desc| desc.1.color) {
-                if nodes
-                    .iter()
-                    .find(|description| description.1.color != color2)
-                    .is_none()
-                {
-                    color = color2;
-                }
+            if let Some(first) = nodes.first().map(|index| self.nodes[*index].color)
+                && nodes.iter().all(|index| self.nodes[*index].color == first)
+            {
+                color = first;
             }
 
             groups.push(NodeGroup {
-                color: color,
-                name: category.0.to_owned(),
-                nodes: nodes.iter().map(|desc| desc.0).collect(),
+                color,
+                name: name.to_owned(),
+                nodes,
             });
-        });
+        }
 
-        if !node_map.is_empty() {
-            let nodes = node_map.drain().map(|(k, v)| v.0).collect();
+        let uncategorized = (0..self.nodes.len())
+            .filter(|index| !assigned[*index])
+            .collect::<Vec<_>>();
+        if !uncategorized.is_empty() {
             groups.push(NodeGroup {
                 color: Default::default(),
                 name: "Uncategorized".to_owned(),
-                nodes,
+                nodes: uncategorized,
             });
         }
 
-        Workspace {
-            workspace: schema,
-            groups: groups,
-            nodes: nodes,
-        }
+        groups
+    }
+
+    /// Finds the descriptor with the given id
+    pub fn get_description(&self, id: &str) -> Option<&Arc<NodeDescription>> {
+        self.by_id.get(id).map(|index| &self.nodes[*index])
+    }
+
+    /// Name of the field selecting the descriptor of a variant, None if `variant` is not a variant
+    pub fn get_variant_field(&self, variant: &str) -> Option<&str> {
+        self.variants
+            .get(variant)
+            .map(|index| index.field_name.as_str())
+    }
+
+    /// Finds the descriptor a variant key resolves to
+    pub fn resolve_variant(&self, variant: &str, key: &str) -> Option<&Arc<NodeDescription>> {
+        self.variants
+            .get(variant)
+            .and_then(|index| index.by_key.get(key))
+            .map(|index| &self.nodes[*index])
+    }
+
+    /// Indices into `nodes` of the existing nodes listed in a category
+    pub fn category_nodes(&self, category: &str) -> &[usize] {
+        self.categories
+            .get(category)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Reverse variant lookup: Finds the variant field and key that resolve to the given descriptor id.
+    /// Returns None if the variant key is not a variant (the descriptor id is used directly in that case)
+    pub fn get_variant_key<'a>(
+        &'a self,
+        variant: &str,
+        descriptor_id: &str,
+    ) -> Option<(&'a str, &'a str)> {
+        self.variants.get(variant).and_then(|index| {
+            index
+                .by_descriptor
+                .get(descriptor_id)
+                .map(|key| (index.field_name.as_str(), key.as_str()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workspace::tests::synthetic_workspace;
+
+    #[test]
+    pub fn workspace_indexes() {
+        let workspace = synthetic_workspace();
+
+        assert_eq!(
+            workspace.get_description("SumDensity").unwrap().title,
+            "Sum"
+        );
+        assert!(workspace.get_description("Missing").is_none());
+
+        assert_eq!(workspace.get_variant_field("Density"), Some("Type"));
+        assert!(workspace.get_variant_field("ConstantDensity").is_none());
+        assert_eq!(
+            workspace.resolve_variant("Density", "Constant").unwrap().id,
+            "ConstantDensity"
+        );
+        assert!(workspace.resolve_variant("Density", "Missing").is_none());
+        assert_eq!(
+            workspace.get_variant_key("Density", "SumDensity"),
+            Some(("Type", "Sum"))
+        );
+
+        let category = workspace
+            .category_nodes("Density")
+            .iter()
+            .map(|index| workspace.nodes[*index].id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(category, ["ConstantDensity", "SumDensity"]);
+        assert!(workspace.category_nodes("Missing").is_empty());
     }
 }